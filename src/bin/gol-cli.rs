@@ -0,0 +1,381 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Runs a pattern for a number of generations without opening any window.
+
+use std::{
+    env, fs,
+    io::{self, Read},
+    path::Path,
+    process::ExitCode,
+};
+
+use game_of_life::{
+    hashlife,
+    pattern::{Format, Pattern},
+    rule::Rule,
+    rule_table::RuleTable,
+    topology::Topology,
+    universe::{CellPosition, Engine, Universe},
+};
+
+const USAGE: &str = "\
+Usage: gol-cli [OPTIONS] <PATTERN_FILE>
+
+Loads a pattern file (RLE, plaintext, Life 1.05 or Life 1.06, '-' for the
+standard input), advances it and prints the final population and bounding box.
+
+Options:
+  -g, --generations <N>    Number of generations to compute [default: 0]
+  -r, --rule <RULE>        Rule overriding the one of the pattern, e.g. B36/S23, B2-a/S12,
+                           B2/S345/C4, WireWorld or R5,C0,M1,S34..58,B34..45,NM
+  -R, --rule-file <FILE>   Golly rule file (@TABLE or @TREE) overriding the rule of
+                           the pattern
+  -t, --topology <TOPO>    infinite, torus:WxH, bounded:WxH, klein:WxH or cross:WxH
+                           [default: infinite]
+  -e, --engine <ENGINE>    sparse, hashlife or dense [default: sparse]
+  -o, --output <FILE>      Writes the final pattern, '-' for the standard output
+  -f, --format <FORMAT>    Format of the output: rle, cells, life105 or life106
+                           [default: guessed from the output extension, or rle]
+  -h, --help               Prints this help";
+
+#[derive(Default)]
+struct Args {
+    input: String,
+    generations: u64,
+    rule: Option<Rule>,
+    rule_file: Option<String>,
+    topology: Topology,
+    engine: Engine,
+    output: Option<String>,
+    format: Option<Format>,
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let input = if args.input == "-" {
+        let mut input = String::new();
+        io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|err| format!("cannot read standard input: {err}"))?;
+        input
+    } else {
+        fs::read_to_string(&args.input)
+            .map_err(|err| format!("cannot read {}: {err}", args.input))?
+    };
+    let pattern = Format::detect(&input)
+        .read(&input)
+        .map_err(|err| format!("invalid pattern: {err}"))?;
+    let rule = match (args.rule, args.rule_file, &pattern.rule) {
+        (Some(rule), _, _) => rule,
+        (None, Some(rule_file), _) => {
+            let content = fs::read_to_string(&rule_file)
+                .map_err(|err| format!("cannot read {rule_file}: {err}"))?;
+            let table = RuleTable::parse(&content)
+                .map_err(|err| format!("invalid rule file {rule_file}: {err}"))?;
+            Rule::from_table(table)
+        }
+        (None, None, Some(pattern_rule)) => pattern_rule
+            .parse()
+            .map_err(|err| format!("unsupported pattern rule {pattern_rule}: {err}"))?,
+        (None, None, None) => Rule::default(),
+    };
+    check_support(&rule, args.topology, args.engine)?;
+
+    let mut universe = Universe::new(rule, args.topology);
+    universe.set_engine(args.engine);
+    universe.paste(&pattern, &CellPosition { x: 0, y: 0 });
+    universe.advance(args.generations);
+    if universe.generation() < args.generations {
+        eprintln!(
+            "warning: stopped at generation {}, the pattern would grow out of the coordinates range or past {} cells",
+            universe.generation(),
+            hashlife::MAX_POPULATION
+        );
+    }
+
+    println!("rule: {}", universe.rule());
+    println!("generation: {}", universe.generation());
+    println!("population: {}", universe.population());
+    match universe.bounding_box() {
+        Some((min, max)) => println!(
+            "bounding box: x = {}..={}, y = {}..={} ({}x{})",
+            min.x,
+            max.x,
+            min.y,
+            max.y,
+            max.x - min.x + 1,
+            max.y - min.y + 1
+        ),
+        None => println!("bounding box: empty"),
+    }
+
+    if let Some(output) = args.output {
+        let format = args
+            .format
+            .or_else(|| {
+                Path::new(&output)
+                    .extension()
+                    .and_then(|extension| Format::from_extension(&extension.to_string_lossy()))
+            })
+            .unwrap_or_default();
+        let mut result = Pattern::from_cells(universe.iter_alive());
+        result.states = universe
+            .iter_dying()
+            .map(|(cell, state)| (cell.clone(), state))
+            .collect();
+        result.name = pattern.name;
+        result.rule = Some(universe.rule().to_string());
+        let content = format.write(&result);
+        if output == "-" {
+            print!("{content}");
+        } else {
+            fs::write(&output, content).map_err(|err| format!("cannot write {output}: {err}"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the topology and the engine can be used with the rule.
+fn check_support(rule: &Rule, topology: Topology, engine: Engine) -> Result<(), String> {
+    if !topology.supports(rule.tiling()) {
+        return Err(format!(
+            "the {} topology cannot join the edges of the {} cells of the {rule} rule",
+            topology.name(),
+            rule.tiling().name().to_lowercase()
+        ));
+    }
+    if !engine.supports(rule, &topology) {
+        return Err(format!(
+            "the {} engine does not support the {} topology with the {rule} rule",
+            engine.name(),
+            topology.name()
+        ));
+    }
+    Ok(())
+}
+
+/// Returns `None` if the help was requested.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args::default();
+    let mut input = None;
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for {arg}"))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-g" | "--generations" => {
+                let value = value()?;
+                parsed.generations = value
+                    .parse()
+                    .map_err(|_| format!("invalid number of generations: {value}"))?;
+            }
+            "-r" | "--rule" => {
+                let value = value()?;
+                parsed.rule = Some(
+                    value
+                        .parse()
+                        .map_err(|err| format!("invalid rule {value}: {err}"))?,
+                );
+            }
+            "-R" | "--rule-file" => parsed.rule_file = Some(value()?),
+            "-t" | "--topology" => parsed.topology = parse_topology(&value()?)?,
+            "-e" | "--engine" => {
+                parsed.engine = match value()?.as_str() {
+                    "sparse" => Engine::Sparse,
+                    "hashlife" => Engine::HashLife,
+                    "dense" => Engine::Dense,
+                    other => return Err(format!("unknown engine: {other}")),
+                }
+            }
+            "-o" | "--output" => parsed.output = Some(value()?),
+            "-f" | "--format" => {
+                parsed.format = Some(match value()?.as_str() {
+                    "rle" => Format::Rle,
+                    "cells" | "plaintext" => Format::Plaintext,
+                    "life105" => Format::Life105,
+                    "life106" => Format::Life106,
+                    other => return Err(format!("unknown format: {other}")),
+                })
+            }
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown option: {arg}"))
+            }
+            _ if input.is_none() => input = Some(arg),
+            _ => return Err(format!("unexpected argument: {arg}")),
+        }
+    }
+    if parsed.rule.is_some() && parsed.rule_file.is_some() {
+        return Err("--rule and --rule-file cannot be used together".to_string());
+    }
+    parsed.input = input.ok_or("missing pattern file")?;
+    Ok(Some(parsed))
+}
+
+fn parse_topology(value: &str) -> Result<Topology, String> {
+    if value == "infinite" {
+        return Ok(Topology::Infinite);
+    }
+    let invalid = || format!("invalid topology: {value}");
+    let (kind, size) = value.split_once(':').ok_or_else(invalid)?;
+    let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+    let width = width.parse().map_err(|_| invalid())?;
+    let height = height.parse().map_err(|_| invalid())?;
+    match kind {
+        "torus" => Ok(Topology::Torus { width, height }),
+        "bounded" => Ok(Topology::Bounded { width, height }),
+        "klein" => Ok(Topology::KleinBottle { width, height }),
+        "cross" => Ok(Topology::CrossSurface { width, height }),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_arguments() {
+        let args = parse(&[
+            "-g",
+            "100",
+            "--rule",
+            "B36/S23",
+            "-t",
+            "torus:30x20",
+            "-e",
+            "dense",
+            "-o",
+            "out.cells",
+            "-f",
+            "life106",
+            "in.rle",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(args.input, "in.rle");
+        assert_eq!(args.generations, 100);
+        assert_eq!(args.rule, Some("B36/S23".parse().unwrap()));
+        assert_eq!(
+            args.topology,
+            Topology::Torus {
+                width: 30,
+                height: 20
+            }
+        );
+        assert_eq!(args.engine, Engine::Dense);
+        assert_eq!(args.output.as_deref(), Some("out.cells"));
+        assert_eq!(args.format, Some(Format::Life106));
+        let args = parse(&["-"]).unwrap().unwrap();
+        assert_eq!(args.input, "-");
+        assert_eq!(args.topology, Topology::Infinite);
+        assert!(parse(&["in.rle", "--help"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        for (args, error) in [
+            (&["--verbose", "in.rle"][..], "unknown option: --verbose"),
+            (&["in.rle", "-g"], "missing value for -g"),
+            (&["-g", "-5", "in.rle"], "invalid number of generations: -5"),
+            (&["-e", "quantum", "in.rle"], "unknown engine: quantum"),
+            (&["-f", "png", "in.rle"], "unknown format: png"),
+            (&["-t", "torus", "in.rle"], "invalid topology: torus"),
+            (
+                &["-t", "torus:10x", "in.rle"],
+                "invalid topology: torus:10x",
+            ),
+            (
+                &["-t", "sphere:10x10", "in.rle"],
+                "invalid topology: sphere:10x10",
+            ),
+            (&["a.rle", "b.rle"], "unexpected argument: b.rle"),
+            (&["-g", "10"], "missing pattern file"),
+            (
+                &["-r", "B3/S23", "-R", "rule.table", "in.rle"],
+                "--rule and --rule-file cannot be used together",
+            ),
+        ] {
+            assert_eq!(parse(args).err().as_deref(), Some(error), "{args:?}");
+        }
+        let error = parse(&["-r", "B9/S23", "in.rle"]).err().unwrap();
+        assert!(error.starts_with("invalid rule B9/S23"), "{error}");
+    }
+
+    #[test]
+    fn checks_engine_and_topology_support() {
+        let torus = Topology::Torus {
+            width: 10,
+            height: 10,
+        };
+        let conway = Rule::conway();
+        let generations: Rule = "B2/S/C3".parse().unwrap();
+        let hexagonal: Rule = "B2/S34H".parse().unwrap();
+        assert!(check_support(&conway, Topology::Infinite, Engine::HashLife).is_ok());
+        assert!(check_support(&conway, torus, Engine::Dense).is_ok());
+        assert!(check_support(&generations, torus, Engine::Sparse).is_ok());
+        for (rule, topology, engine) in [
+            (&conway, torus, Engine::HashLife),
+            (&conway, Topology::Infinite, Engine::Dense),
+            (&generations, Topology::Infinite, Engine::HashLife),
+            (&generations, torus, Engine::Dense),
+            (&hexagonal, Topology::Infinite, Engine::HashLife),
+        ] {
+            let error = check_support(rule, topology, engine).unwrap_err();
+            assert!(error.contains("engine does not support"), "{error}");
+        }
+        let klein = Topology::KleinBottle {
+            width: 10,
+            height: 10,
+        };
+        let error = check_support(&hexagonal, klein, Engine::Sparse).unwrap_err();
+        assert!(error.contains("cannot join the edges"), "{error}");
+    }
+}
@@ -21,28 +21,116 @@ LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
 OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
-use std::{collections::BTreeSet, time::Duration};
+use std::{collections::VecDeque, time::Duration};
 
-use bevy::{prelude::*, utils::HashMap};
+use crate::turmite_system::ants_walking;
+use bevy::prelude::*;
+use game_of_life::{
+    history::{Change, Timeline, UndoHistory},
+    library,
+    topology::Topology,
+    universe::{CellPosition, Engine, Universe},
+};
 
-static NEIGHBOURS_DELTA: [(isize, isize); 8] = [
-    (-1, -1),
-    (0, -1),
-    (1, -1),
-    (-1, 0),
-    (1, 0),
-    (-1, 1),
-    (0, 1),
-    (1, 1),
-];
+const HISTORY_CAPACITY: usize = 500;
+const TIMELINE_MAX_CELLS: usize = 2_000_000;
+const POPULATION_PLOT_POINTS: usize = 1000;
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub struct CellSet;
 
-#[derive(Clone, Component, PartialEq, Eq, Debug, Hash, PartialOrd, Ord)]
-pub struct CellPosition {
-    pub x: isize,
-    pub y: isize,
+/// Application of the [`PendingEdits`], before the next generation is computed.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct EditSet;
+
+/// Modification of the cells.
+pub type Edit = Box<dyn FnOnce(&mut Universe) + Send + Sync>;
+
+/// Source of truth for the cells.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct CellUniverse(pub Universe);
+
+/// Modifications of the [`CellUniverse`] which can be undone.
+#[derive(Resource, Deref, DerefMut)]
+pub struct CellHistory(pub UndoHistory);
+
+impl Default for CellHistory {
+    fn default() -> Self {
+        Self(UndoHistory::new(HISTORY_CAPACITY))
+    }
+}
+
+/// Modifications of the [`CellUniverse`] made while playing, applied between
+/// two generations.
+#[derive(Resource, Default)]
+pub struct PendingEdits {
+    edits: Vec<Edit>,
+    /// Whether the group of the undo history ends once the edits are applied.
+    end_group: bool,
+}
+
+impl PendingEdits {
+    pub fn push(&mut self, edit: Edit) {
+        self.edits.push(edit);
+    }
+
+    /// Ends the group of the [`CellHistory`] after the queued edits, see
+    /// [`UndoHistory::start_group`].
+    pub fn end_group(&mut self) {
+        self.end_group = true;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty() && !self.end_group
+    }
+}
+
+/// Past generations of the [`CellUniverse`].
+#[derive(Resource, Deref, DerefMut)]
+pub struct CellTimeline(pub Timeline);
+
+impl Default for CellTimeline {
+    fn default() -> Self {
+        Self(Timeline::new(TIMELINE_MAX_CELLS))
+    }
+}
+
+/// Figures about the [`CellUniverse`], births and deaths being the ones of
+/// the last computed step.
+#[derive(Resource, Debug, Default)]
+pub struct SimulationStats {
+    pub generation: u64,
+    pub population: usize,
+    pub births: usize,
+    pub deaths: usize,
+    pub bounding_box: Option<(CellPosition, CellPosition)>,
+    /// Generation and population of the last computed steps.
+    pub population_history: VecDeque<[f64; 2]>,
+    /// Whether the last step computed less generations than requested, see
+    /// [`Universe::advance`].
+    pub stopped_early: bool,
+}
+
+impl SimulationStats {
+    pub fn record_step(&mut self, universe: &Universe, change: &Change) {
+        self.births = change.births();
+        self.deaths = change.deaths();
+        let generation = universe.generation() as f64;
+        while matches!(self.population_history.back(), Some([last, _]) if *last >= generation) {
+            self.population_history.pop_back();
+        }
+        if self.population_history.len() == POPULATION_PLOT_POINTS {
+            self.population_history.pop_front();
+        }
+        self.population_history
+            .push_back([generation, universe.population() as f64]);
+    }
+
+    fn refresh(&mut self, universe: &Universe) {
+        self.generation = universe.generation();
+        self.population = universe.population();
+        self.bounding_box = universe.bounding_box();
+    }
 }
 
 #[derive(Resource, Debug)]
@@ -50,6 +138,9 @@ pub struct CellParams {
     pub playing: bool,
     pub period: Duration,
     pub compute_next_generation: bool,
+    pub topology: Topology,
+    pub engine: Engine,
+    pub hashlife_step_log2: u8,
 }
 
 impl Default for CellParams {
@@ -58,6 +149,9 @@ impl Default for CellParams {
             playing: true,
             period: Duration::from_secs(1),
             compute_next_generation: false,
+            topology: Topology::default(),
+            engine: Engine::default(),
+            hashlife_step_log2: 0,
         }
     }
 }
@@ -65,6 +159,19 @@ impl Default for CellParams {
 #[derive(Resource)]
 pub struct NextGenTimer(Timer);
 
+impl NextGenTimer {
+    /// Whether the next step is to be computed, either because the period
+    /// elapsed while playing or because a single step was requested.
+    pub fn is_step_due(&mut self, cell_params: &mut CellParams, time: &Time) -> bool {
+        if cell_params.playing {
+            self.0.tick(time.delta());
+            self.0.finished()
+        } else {
+            std::mem::take(&mut cell_params.compute_next_generation)
+        }
+    }
+}
+
 pub struct CellSystem;
 
 impl Plugin for CellSystem {
@@ -72,22 +179,49 @@ impl Plugin for CellSystem {
         let cell_params = CellParams::default();
         let period = cell_params.period;
         app.insert_resource(cell_params)
+            .init_resource::<CellUniverse>()
+            .init_resource::<CellHistory>()
+            .init_resource::<CellTimeline>()
+            .init_resource::<SimulationStats>()
+            .init_resource::<PendingEdits>()
             .insert_resource(NextGenTimer(Timer::new(period, TimerMode::Repeating)))
-            .add_systems(Update, check_cell_params_changed)
+            .configure_set(Update, EditSet.before(CellSet))
+            .add_systems(Update, check_cell_params_changed.before(CellSet))
+            .add_systems(
+                Update,
+                system_apply_edits
+                    .in_set(EditSet)
+                    .run_if(|pending: Res<PendingEdits>| !pending.is_empty()),
+            )
             .add_systems(Startup, init_cells.in_set(CellSet))
-            .add_systems(Update, system_cells.in_set(CellSet));
+            // Ants walking on the cells replace the generations of the rule,
+            // the colours they leave behind would otherwise evolve.
+            .add_systems(
+                Update,
+                system_cells.in_set(CellSet).run_if(not(ants_walking)),
+            )
+            .add_systems(
+                Update,
+                (system_record_timeline, system_refresh_stats)
+                    .in_set(CellSet)
+                    .after(system_cells)
+                    .run_if(resource_changed::<CellUniverse>()),
+            );
     }
 }
 
-fn init_cells(mut commands: Commands) {
-    commands.spawn(CellPosition { x: 0, y: 0 });
-    commands.spawn(CellPosition { x: -1, y: 0 });
-    commands.spawn(CellPosition { x: 0, y: -1 });
-    commands.spawn(CellPosition { x: 0, y: 1 });
-    commands.spawn(CellPosition { x: 1, y: 1 });
+fn init_cells(mut universe: ResMut<CellUniverse>) {
+    if let Some(r_pentomino) = library::find("R-pentomino") {
+        let pattern = r_pentomino.pattern().centered();
+        universe.paste(&pattern, &CellPosition { x: 0, y: 0 });
+    }
 }
 
-fn check_cell_params_changed(my_res: Res<CellParams>, mut timer: ResMut<NextGenTimer>) {
+fn check_cell_params_changed(
+    my_res: Res<CellParams>,
+    mut timer: ResMut<NextGenTimer>,
+    mut universe: ResMut<CellUniverse>,
+) {
     if !my_res.is_changed() {
         return;
     }
@@ -96,62 +230,59 @@ fn check_cell_params_changed(my_res: Res<CellParams>, mut timer: ResMut<NextGenT
         timer.0.set_duration(my_res.period);
         timer.0.reset();
     }
+    if my_res.topology != universe.topology() {
+        universe.set_topology(my_res.topology);
+    }
+    if my_res.engine != universe.engine() {
+        universe.set_engine(my_res.engine);
+    }
 }
 
 fn system_cells(
-    mut commands: Commands,
-    query: Query<(Entity, &CellPosition)>,
     mut timer: ResMut<NextGenTimer>,
     mut cell_params: ResMut<CellParams>,
+    mut universe: ResMut<CellUniverse>,
+    mut history: ResMut<CellHistory>,
+    mut stats: ResMut<SimulationStats>,
     time: Res<Time>,
 ) {
-    if cell_params.playing {
-        timer.0.tick(time.delta());
-        if !timer.0.finished() {
-            return;
-        }
-    } else if cell_params.compute_next_generation {
-        cell_params.compute_next_generation = false;
-    } else {
+    if !timer.is_step_due(&mut cell_params, &time) {
         return;
     }
-    let mut neighbours = HashMap::new();
-    let mut spawn_candidates = BTreeSet::new();
-    // Compute number of alive neighbour cells
-    for (_, cell) in &query {
-        for pos_delta in NEIGHBOURS_DELTA.iter() {
-            let scan_pos = CellPosition {
-                x: cell.x + pos_delta.0,
-                y: cell.y + pos_delta.1,
-            };
-            let neighbours_count = match neighbours.get(&scan_pos) {
-                Some(prev_val) => prev_val + 1,
-                None => 1,
-            };
-            neighbours.insert(scan_pos.clone(), neighbours_count);
-            if neighbours_count == 3 {
-                // This cell has 3 neighours, might spawn a new cell
-                spawn_candidates.insert(scan_pos.clone());
-            } else if neighbours_count == 4 {
-                // Actually, this cell has too many neighbours, removing the candidate
-                spawn_candidates.remove(&scan_pos);
-            }
-        }
+    let step_log2 = match cell_params.engine {
+        Engine::HashLife => cell_params.hashlife_step_log2,
+        Engine::Sparse | Engine::Dense => 0,
+    };
+    let requested = universe.generation().saturating_add(1 << step_log2);
+    let change = Change::step(&mut universe.0, step_log2);
+    stats.record_step(&universe, &change);
+    stats.stopped_early = universe.generation() < requested;
+    if stats.stopped_early {
+        cell_params.playing = false;
     }
-    // Killing starved or overpopulated cells
-    for (entity, cell) in &query {
-        let neighbours_count = *neighbours.get(cell).unwrap_or(&0);
-        match neighbours_count {
-            0..=1 => commands.entity(entity).despawn(),
-            2 => (),
-            3 => {
-                spawn_candidates.remove(cell);
-            }
-            _ => commands.entity(entity).despawn(),
+    history.push(change);
+}
+
+/// Applies the edits of the frame at once, as a single modification.
+fn system_apply_edits(
+    mut pending: ResMut<PendingEdits>,
+    mut universe: ResMut<CellUniverse>,
+    mut history: ResMut<CellHistory>,
+) {
+    history.record(&mut universe.0, |universe| {
+        for edit in pending.edits.drain(..) {
+            edit(universe);
         }
+    });
+    if std::mem::take(&mut pending.end_group) {
+        history.end_group(&universe);
     }
-    // Spawn new cells
-    for new_cell in spawn_candidates {
-        commands.spawn(new_cell);
-    }
+}
+
+fn system_record_timeline(universe: Res<CellUniverse>, mut timeline: ResMut<CellTimeline>) {
+    timeline.record(&universe);
+}
+
+fn system_refresh_stats(universe: Res<CellUniverse>, mut stats: ResMut<SimulationStats>) {
+    stats.refresh(&universe);
 }
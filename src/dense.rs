@@ -0,0 +1,255 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Bit-packed grid for finite boards, computing the neighbour counts of 64
+//! cells at once with bitwise adders.
+//!
+//! Each row is surrounded by a one cell wide halo, filled before every
+//! generation with the cells designated by the topology, so that wrapped
+//! boards do not need any special case while counting.
+
+use std::thread;
+
+use crate::{parallel, rule::Rule, topology::Topology, universe::CellPosition};
+
+type Word = u64;
+/// Column and row in the grid, the board starting at `(1, 1)`.
+type GridPosition = (usize, usize);
+
+const WORD_BITS: usize = Word::BITS as usize;
+
+#[derive(Default)]
+pub struct DenseGrid {
+    topology: Topology,
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    cells: Vec<Word>,
+    next: Vec<Word>,
+    /// Halo cells, with the board cell they mirror if any.
+    halo: Vec<(GridPosition, Option<GridPosition>)>,
+    /// Bits of the board cells on a row, excluding the halo.
+    row_mask: Vec<Word>,
+}
+
+impl DenseGrid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the content of the grid. Cells outside of the board are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the topology is not finite.
+    pub fn load<'a>(&mut self, topology: Topology, cells: impl Iterator<Item = &'a CellPosition>) {
+        if topology != self.topology || self.cells.is_empty() {
+            self.resize(topology);
+        } else {
+            self.cells.fill(0);
+        }
+        let (min, _) = topology.bounds().expect("dense grids are finite");
+        for cell in cells.filter(|cell| topology.contains(cell)) {
+            let x = (cell.x - min.x) as usize + 1;
+            let y = (cell.y - min.y) as usize + 1;
+            self.set(x, y, true);
+        }
+    }
+
+    fn resize(&mut self, topology: Topology) {
+        let (width, height) = topology.size().expect("dense grids are finite");
+        let (min, _) = topology.bounds().unwrap();
+        self.topology = topology;
+        self.width = width;
+        self.height = height;
+        self.words_per_row = (width + 2).div_ceil(WORD_BITS);
+        self.cells = vec![0; self.words_per_row * (height + 2)];
+        self.next = self.cells.clone();
+        self.row_mask = vec![0; self.words_per_row];
+        for x in 1..=width {
+            self.row_mask[x / WORD_BITS] |= 1 << (x % WORD_BITS);
+        }
+        self.halo.clear();
+        for y in 0..height + 2 {
+            for x in 0..width + 2 {
+                if (1..=width).contains(&x) && (1..=height).contains(&y) {
+                    continue;
+                }
+                let source = topology
+                    .wrap(&CellPosition {
+                        x: min.x + x as isize - 1,
+                        y: min.y + y as isize - 1,
+                    })
+                    .map(|cell| ((cell.x - min.x) as usize + 1, (cell.y - min.y) as usize + 1));
+                self.halo.push(((x, y), source));
+            }
+        }
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells[self.words_per_row..(self.height + 1) * self.words_per_row]
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn cells(&self) -> Vec<CellPosition> {
+        self.board_cells(|cells, _| cells)
+    }
+
+    /// Cells which died and cells which were born during the last step.
+    pub fn last_changes(&self) -> (Vec<CellPosition>, Vec<CellPosition>) {
+        (
+            self.board_cells(|cells, previous| previous & !cells),
+            self.board_cells(|cells, previous| cells & !previous),
+        )
+    }
+
+    /// Cells of the board whose bit is set in the word computed from the
+    /// words of the current and previous generations.
+    fn board_cells(&self, word: impl Fn(Word, Word) -> Word) -> Vec<CellPosition> {
+        let Some((min, _)) = self.topology.bounds() else {
+            return Vec::new();
+        };
+        let mut cells = Vec::new();
+        for y in 1..=self.height {
+            let row = y * self.words_per_row..(y + 1) * self.words_per_row;
+            let words = self.cells[row.clone()].iter().zip(&self.next[row]);
+            for (word_idx, (cells_word, previous_word)) in words.enumerate() {
+                // The previous generation holds the halo
+                let mut word = word(*cells_word, *previous_word) & self.row_mask[word_idx];
+                while word != 0 {
+                    let x = word_idx * WORD_BITS + word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    cells.push(CellPosition {
+                        x: min.x + x as isize - 1,
+                        y: min.y + y as isize - 1,
+                    });
+                }
+            }
+        }
+        cells
+    }
+
+    /// Computes the next generation.
+    pub fn step(&mut self, rule: &Rule) {
+        let halo = std::mem::take(&mut self.halo);
+        for &((x, y), source) in &halo {
+            let alive = source.is_some_and(|(source_x, source_y)| self.get(source_x, source_y));
+            self.set(x, y, alive);
+        }
+        self.halo = halo;
+        let survival_counts: Vec<usize> = (0..=8).filter(|&n| rule.is_survival(n)).collect();
+        let birth_counts: Vec<usize> = (0..=8).filter(|&n| rule.is_birth(n)).collect();
+        let words = self.words_per_row;
+        let (cells, row_mask) = (&self.cells, &self.row_mask);
+        let next_row = |y: usize, output: &mut [Word]| {
+            let above = &cells[(y - 1) * words..y * words];
+            let center = &cells[y * words..(y + 1) * words];
+            let below = &cells[(y + 1) * words..(y + 2) * words];
+            for (word_idx, output) in output.iter_mut().enumerate() {
+                let neighbours = [
+                    west(above, word_idx),
+                    above[word_idx],
+                    east(above, word_idx),
+                    west(center, word_idx),
+                    east(center, word_idx),
+                    west(below, word_idx),
+                    below[word_idx],
+                    east(below, word_idx),
+                ];
+                // Neighbour counts, bit sliced: counts[i] holds the bits of weight 2^i
+                let mut counts: [Word; 4] = [0; 4];
+                for neighbour in neighbours {
+                    let mut carry = neighbour;
+                    for count in counts.iter_mut() {
+                        (*count, carry) = (*count ^ carry, *count & carry);
+                    }
+                }
+                let has_count = |n: usize| {
+                    counts.iter().enumerate().fold(!0, |mask, (bit, count)| {
+                        mask & if n & (1 << bit) != 0 { *count } else { !*count }
+                    })
+                };
+                let survives = survival_counts
+                    .iter()
+                    .fold(0, |mask, &n| mask | has_count(n));
+                let born = birth_counts.iter().fold(0, |mask, &n| mask | has_count(n));
+                let alive = center[word_idx];
+                *output = ((alive & survives) | (!alive & born)) & row_mask[word_idx];
+            }
+        };
+        // Rows are split in bands computed by separate threads
+        let threads = parallel::thread_count(self.width * self.height);
+        let band_rows = self.height.div_ceil(threads).max(1);
+        let next_rows = &mut self.next[words..(self.height + 1) * words];
+        let next_band = |band_idx: usize, band: &mut [Word]| {
+            for (row_idx, row) in band.chunks_mut(words).enumerate() {
+                next_row(1 + band_idx * band_rows + row_idx, row);
+            }
+        };
+        if threads == 1 {
+            next_band(0, next_rows);
+        } else {
+            thread::scope(|scope| {
+                for (band_idx, band) in next_rows.chunks_mut(band_rows * words).enumerate() {
+                    scope.spawn(move || next_band(band_idx, band));
+                }
+            });
+        }
+        std::mem::swap(&mut self.cells, &mut self.next);
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.cells[y * self.words_per_row + x / WORD_BITS] & (1 << (x % WORD_BITS)) != 0
+    }
+
+    fn set(&mut self, x: usize, y: usize, alive: bool) {
+        let word = &mut self.cells[y * self.words_per_row + x / WORD_BITS];
+        if alive {
+            *word |= 1 << (x % WORD_BITS);
+        } else {
+            *word &= !(1 << (x % WORD_BITS));
+        }
+    }
+}
+
+/// Row shifted so that each bit holds its west neighbour.
+fn west(row: &[Word], word_idx: usize) -> Word {
+    let carry = if word_idx > 0 {
+        row[word_idx - 1] >> (WORD_BITS - 1)
+    } else {
+        0
+    };
+    (row[word_idx] << 1) | carry
+}
+
+/// Row shifted so that each bit holds its east neighbour.
+fn east(row: &[Word], word_idx: usize) -> Word {
+    let carry = match row.get(word_idx + 1) {
+        Some(next) => next << (WORD_BITS - 1),
+        None => 0,
+    };
+    (row[word_idx] >> 1) | carry
+}
@@ -0,0 +1,135 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Cells being drawn with the mouse.
+
+use crate::cell_system::Edit;
+use bevy::prelude::*;
+use game_of_life::{
+    shape,
+    universe::{CellPosition, Universe},
+};
+
+/// Shape drawn from the cell where the mouse button was pressed to the cell
+/// where it is released.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shape {
+    Line,
+    Rectangle,
+    Ellipse,
+}
+
+impl Shape {
+    pub fn cells(
+        &self,
+        start: &CellPosition,
+        end: &CellPosition,
+        filled: bool,
+    ) -> Vec<CellPosition> {
+        match self {
+            Self::Line => shape::line(start, end),
+            Self::Rectangle => shape::rectangle(start, end, filled),
+            Self::Ellipse => shape::ellipse(start, end, filled),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Drawing {
+    /// Cell where the mouse button was pressed.
+    start: Option<CellPosition>,
+    /// Last cell painted by a freehand stroke.
+    last: Option<CellPosition>,
+}
+
+impl Drawing {
+    pub fn start(&self) -> Option<&CellPosition> {
+        self.start.as_ref()
+    }
+
+    pub fn is_drawing(&self) -> bool {
+        self.start.is_some()
+    }
+
+    /// Starts a freehand stroke, returning the first cell to paint.
+    pub fn start_stroke(&mut self, cell: CellPosition) -> Vec<CellPosition> {
+        self.start = Some(cell.clone());
+        self.last = Some(cell.clone());
+        vec![cell]
+    }
+
+    /// Cells from the last painted one to the given one, so that fast mouse
+    /// moves do not leave gaps.
+    pub fn continue_stroke(&mut self, cell: &CellPosition) -> Vec<CellPosition> {
+        match &self.last {
+            Some(last) if last != cell => {
+                let cells = shape::line(last, cell);
+                self.last = Some(cell.clone());
+                cells
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Ends a freehand stroke, returning whether one was drawn.
+    pub fn end_stroke(&mut self) -> bool {
+        self.start = None;
+        self.last.take().is_some()
+    }
+
+    pub fn start_shape(&mut self, cell: CellPosition) {
+        self.start = Some(cell);
+    }
+
+    /// Cells of the shape from the cell where it was started to the given one.
+    pub fn end_shape(
+        &mut self,
+        shape: Shape,
+        filled: bool,
+        end: &CellPosition,
+    ) -> Option<Vec<CellPosition>> {
+        let start = self.start.take()?;
+        Some(shape.cells(&start, end, filled))
+    }
+
+    /// Stops drawing a shape without painting it. No effect on a freehand
+    /// stroke, which ends with [`Self::end_stroke`].
+    pub fn cancel_shape(&mut self) {
+        if self.last.is_none() {
+            self.start = None;
+        }
+    }
+}
+
+/// Sets the state of the cells of the board among the given ones.
+pub fn paint(cells: Vec<CellPosition>, state: u8) -> Edit {
+    Box::new(move |universe: &mut Universe| {
+        let topology = universe.topology();
+        for cell in cells {
+            if topology.contains(&cell) {
+                universe.set_state(cell, state);
+            }
+        }
+    })
+}
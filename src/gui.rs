@@ -24,50 +24,186 @@ SOFTWARE.
 
 use std::time::Duration;
 
-use crate::cell_system::{CellParams, CellPosition, CellSet};
-use bevy::{prelude::*, window::PrimaryWindow};
+use crate::{
+    cell_system::{
+        CellHistory, CellParams, CellSet, CellTimeline, CellUniverse, Edit, EditSet, PendingEdits,
+        SimulationStats,
+    },
+    drawing::{self, Drawing, Shape},
+    selection::{Clipboard, Selection},
+    turmite_system::CellTurmites,
+};
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, PrimitiveTopology, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    window::PrimaryWindow,
+};
 use bevy_egui::{
-    egui::{self, Color32, Ui},
+    egui::{
+        self,
+        plot::{Line, Plot, PlotPoints},
+        Color32, Ui,
+    },
     EguiContexts, EguiPlugin,
 };
 use egui_modal::Modal;
+use game_of_life::{
+    hashlife,
+    history::UndoHistory,
+    library::{self, Category, LibraryPattern},
+    pattern::{Format, Pattern, Transformation},
+    rule::{Rule, RuleParseError},
+    rule_table::RuleTable,
+    tiling::Tiling,
+    topology::Topology,
+    turmite::{Ant, Heading, TurmiteRule},
+    universe::{CellPosition, Engine, Universe},
+};
 use rand::Rng;
 
 type Seconds = f32;
 
 const BACKGROUND_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
 const CELL_COLOR: Color = Color::rgb(0.0, 0.0, 0.2);
+const DYING_COLOR: Color = Color::rgb(0.8, 0.1, 0.1);
+const DYING_FADED_COLOR: Color = Color::rgb(1.0, 0.8, 0.6);
 const SCALE_DEFAULT: f32 = 1.0 / 40.0;
 const SCALE_MAX: f32 = 1.0;
 
 const PERIOD_MIN: Seconds = 0.01;
 const PERIOD_MAX: Seconds = 1.5;
 
+/// Largest texture side supported everywhere, including WebGL2.
+const TEXTURE_SIZE_MAX: usize = 2048;
+
+/// Difference of hue between the colours of successive ants, in degrees.
+const ANT_HUE_STEP: f32 = 137.5;
+/// States above which the paint palette is a number instead of buttons.
+const MAX_PAINT_BUTTONS: u8 = 16;
+/// Undo, redo and the timeline only restore the cells, the ants would keep
+/// walking from their current positions.
+const ANTS_NO_HISTORY: &str = "Not available while ants are walking";
+/// See [`Topology::supports`].
+const UNSUPPORTED_TOPOLOGY: &str =
+    "The edges of this board cannot be joined with the cells of the rule";
+
 pub struct GuiSystem;
 
 impl Plugin for GuiSystem {
     fn build(&self, app: &mut App) {
         app.insert_resource(ClearColor(BACKGROUND_COLOR))
             .insert_resource(GuiParams::default())
+            .init_resource::<Selection>()
+            .init_resource::<Drawing>()
             .add_plugins(EguiPlugin)
-            .add_systems(Startup, init_camera)
-            .add_systems(Update, system_gui)
-            .add_systems(Update, system_mouse_click)
-            .add_systems(Update, system_keyboard_input)
-            .add_systems(Update, system_draw_new_cells.before(CellSet))
+            .add_systems(Startup, (init_camera, init_cell_texture, init_cell_mesh))
+            .add_systems(
+                Update,
+                (system_gui, system_mouse_click, system_keyboard_input).before(EditSet),
+            )
+            .add_systems(
+                Update,
+                (system_draw_cells, system_draw_cell_mesh, system_draw_ants).after(CellSet),
+            )
             .add_systems(
                 Update,
                 system_draw_grid
-                    .after(system_draw_new_cells)
+                    .after(system_draw_cells)
+                    .after(system_draw_cell_mesh)
                     .run_if(|gui_params: Res<GuiParams>| gui_params.grid_enabled),
+            )
+            .add_systems(
+                Update,
+                system_draw_board_border
+                    .after(system_draw_grid)
+                    .run_if(|cell_params: Res<CellParams>| cell_params.topology.size().is_some()),
+            )
+            .add_systems(
+                Update,
+                system_draw_edit_preview.after(system_draw_board_border),
             );
     }
 }
 
+/// Effect of the left mouse button on the board.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EditTool {
+    /// Toggles the clicked cell.
+    #[default]
+    Toggle,
+    /// Paints the cells under the cursor while dragging.
+    Pen,
+    /// Kills the cells under the cursor while dragging.
+    Eraser,
+    /// Paints a shape from the cell where dragging starts to the one where it ends.
+    Shape(Shape),
+    /// Paints the area of cells in the same state around the clicked one,
+    /// within the visible cells.
+    FloodFill,
+    /// Selects a rectangle of cells by dragging.
+    Select,
+}
+
+impl EditTool {
+    pub const ALL: [Self; 8] = [
+        Self::Toggle,
+        Self::Pen,
+        Self::Eraser,
+        Self::Shape(Shape::Line),
+        Self::Shape(Shape::Rectangle),
+        Self::Shape(Shape::Ellipse),
+        Self::FloodFill,
+        Self::Select,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Toggle => "Toggle",
+            Self::Pen => "Pen",
+            Self::Eraser => "Eraser",
+            Self::Shape(Shape::Line) => "Line",
+            Self::Shape(Shape::Rectangle) => "Rectangle",
+            Self::Shape(Shape::Ellipse) => "Ellipse",
+            Self::FloodFill => "Fill",
+            Self::Select => "Select",
+        }
+    }
+}
+
 #[derive(Resource, Debug)]
 pub struct GuiParams {
     pub random_drag_value: u16,
     pub grid_enabled: bool,
+    pub rule_text: String,
+    pub rule_error: Option<String>,
+    pub board_width: u16,
+    pub board_height: u16,
+    pub pattern_text: String,
+    pub pattern_message: Option<String>,
+    pub pattern_format: Format,
+    pub pattern_centered: bool,
+    /// Rules loaded from Golly rule files, referred to by their name.
+    pub loaded_rules: Vec<Rule>,
+    /// State given to the clicked cells when the rule has more than two states.
+    pub paint_state: u8,
+    pub tool: EditTool,
+    /// Whether rectangles and ellipses are drawn filled, or only their outline.
+    pub filled_shapes: bool,
+    pub turmite_text: String,
+    pub turmite_error: Option<String>,
+    /// Rule replaced when adding an ant, for its colours to be cells states.
+    pub turmite_rule_change: Option<String>,
+    /// Whether the board may be modified with the mouse while playing.
+    pub edit_while_playing: bool,
+    pub library_search: String,
+    /// Category the library is filtered on, all of them if `None`.
+    pub library_category: Option<Category>,
+    pub library_selected: Option<&'static LibraryPattern>,
 }
 
 impl Default for GuiParams {
@@ -75,8 +211,194 @@ impl Default for GuiParams {
         Self {
             random_drag_value: 50_u16,
             grid_enabled: true,
+            rule_text: Rule::default().to_string(),
+            rule_error: None,
+            board_width: 100_u16,
+            board_height: 100_u16,
+            pattern_text: String::new(),
+            pattern_message: None,
+            pattern_format: Format::default(),
+            pattern_centered: true,
+            loaded_rules: Vec::new(),
+            paint_state: 1,
+            tool: EditTool::default(),
+            filled_shapes: false,
+            turmite_text: TurmiteRule::default().to_string(),
+            turmite_error: None,
+            turmite_rule_change: None,
+            edit_while_playing: false,
+            library_search: String::new(),
+            library_category: None,
+            library_selected: None,
+        }
+    }
+}
+
+impl GuiParams {
+    /// State given to the clicked, drawn or filled cells.
+    fn state_to_paint(&self, rule: &Rule) -> u8 {
+        if rule.states() > 2 {
+            self.paint_state.min(rule.states() - 1)
+        } else {
+            1
+        }
+    }
+}
+
+/// Resources of the simulation edited from the main window.
+#[derive(SystemParam)]
+struct Simulation<'w> {
+    universe: ResMut<'w, CellUniverse>,
+    history: ResMut<'w, CellHistory>,
+    timeline: ResMut<'w, CellTimeline>,
+    stats: Res<'w, SimulationStats>,
+    turmites: ResMut<'w, CellTurmites>,
+    selection: ResMut<'w, Selection>,
+    pending: ResMut<'w, PendingEdits>,
+}
+
+/// Cells edited with the mouse or the keyboard, along with the undo history.
+#[derive(SystemParam)]
+struct CellEditing<'w> {
+    universe: ResMut<'w, CellUniverse>,
+    history: ResMut<'w, CellHistory>,
+    pending: ResMut<'w, PendingEdits>,
+    selection: ResMut<'w, Selection>,
+    drawing: ResMut<'w, Drawing>,
+}
+
+impl CellEditing<'_> {
+    /// Applies an edit at once and records it, or queues it until the next
+    /// generation while playing.
+    fn apply(&mut self, edit: Option<Edit>, queued: bool) {
+        apply_edit(
+            edit,
+            queued,
+            &mut self.universe,
+            &mut self.history,
+            &mut self.pending,
+        );
+    }
+
+    /// Merges the following edits into a single change of the history, until
+    /// [`Self::end_group`] is called. The generations computed meanwhile are
+    /// part of it, so that undoing it restores a state which existed.
+    fn start_group(&mut self) {
+        self.history.start_group(&self.universe);
+    }
+
+    /// Ends the group once the queued edits are applied.
+    fn end_group(&mut self, queued: bool) {
+        if queued {
+            self.pending.end_group();
+        } else {
+            self.history.end_group(&self.universe);
+        }
+    }
+}
+
+/// See [`CellEditing::apply`].
+fn apply_edit(
+    edit: Option<Edit>,
+    queued: bool,
+    universe: &mut Universe,
+    history: &mut UndoHistory,
+    pending: &mut PendingEdits,
+) {
+    match edit {
+        Some(edit) if queued => pending.push(edit),
+        Some(edit) => history.record(universe, edit),
+        None => (),
+    }
+}
+
+/// Sprite displaying the alive cells of the visible part of the universe.
+#[derive(Component, Default)]
+struct CellTexture {
+    area: VisibleArea,
+}
+
+/// Cells covered by the camera, one texture pixel standing for
+/// `stride`×`stride` cells when zoomed out past [`TEXTURE_SIZE_MAX`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct VisibleArea {
+    x_min: isize,
+    y_max: isize,
+    width: usize,
+    height: usize,
+    stride: usize,
+}
+
+impl VisibleArea {
+    fn new(camera_proj: &OrthographicProjection, camera_transform: &Transform) -> Self {
+        let center = camera_transform.translation.truncate();
+        let (min, max) = (camera_proj.area.min + center, camera_proj.area.max + center);
+        let (x_min, x_max) = (
+            (min.x - 0.5).floor() as isize,
+            (max.x + 0.5).ceil() as isize,
+        );
+        let (y_min, y_max) = (
+            (min.y - 0.5).floor() as isize,
+            (max.y + 0.5).ceil() as isize,
+        );
+        let (width, height) = ((x_max - x_min + 1) as usize, (y_max - y_min + 1) as usize);
+        Self {
+            x_min,
+            y_max,
+            width,
+            height,
+            stride: width.max(height).div_ceil(TEXTURE_SIZE_MAX).max(1),
+        }
+    }
+
+    fn texture_size(&self) -> Extent3d {
+        Extent3d {
+            width: self.width.div_ceil(self.stride) as u32,
+            height: self.height.div_ceil(self.stride) as u32,
+            depth_or_array_layers: 1,
         }
     }
+
+    /// Index of the texture pixel containing the cell, if visible.
+    fn pixel_index(&self, cell: &CellPosition) -> Option<usize> {
+        let column = usize::try_from(cell.x - self.x_min).ok()?;
+        let row = usize::try_from(self.y_max - cell.y).ok()?;
+        if column >= self.width || row >= self.height {
+            return None;
+        }
+        let texture_width = self.width.div_ceil(self.stride);
+        Some(row / self.stride * texture_width + column / self.stride)
+    }
+
+    fn cells(&self) -> impl Iterator<Item = CellPosition> + '_ {
+        (0..self.height as isize).flat_map(move |row| {
+            (0..self.width as isize).map(move |column| CellPosition {
+                x: self.x_min + column,
+                y: self.y_max - row,
+            })
+        })
+    }
+
+    /// Sprite size and position covering the texture.
+    fn sprite_rect(&self) -> Rect {
+        let size = Vec2::new(
+            (self.width.div_ceil(self.stride) * self.stride) as f32,
+            (self.height.div_ceil(self.stride) * self.stride) as f32,
+        );
+        let top_left = Vec2::new(self.x_min as f32 - 0.5, self.y_max as f32 + 0.5);
+        Rect::from_corners(top_left, top_left + Vec2::new(size.x, -size.y))
+    }
+}
+
+/// Ant of the [`CellTurmites`] at some index, each one with its own colour.
+#[derive(Component)]
+struct AntSprite(usize);
+
+/// Mesh displaying the alive cells of the visible part of the universe, for
+/// the tilings other than the square one.
+#[derive(Component, Default)]
+struct CellMesh {
+    area: Option<Rect>,
 }
 
 fn init_camera(mut commands: Commands) {
@@ -85,14 +407,59 @@ fn init_camera(mut commands: Commands) {
     commands.spawn(camera);
 }
 
-fn system_gui(
+fn init_cell_mesh(
     mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, Vec::<[f32; 3]>::new());
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, Vec::<[f32; 4]>::new());
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(mesh).into(),
+            material: materials.add(ColorMaterial::from(Color::WHITE)),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        CellMesh::default(),
+    ));
+}
+
+fn init_cell_texture(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let mut image = Image::new_fill(
+        Extent3d::default(),
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler_descriptor = ImageSampler::nearest();
+    commands.spawn((
+        SpriteBundle {
+            texture: images.add(image),
+            ..Default::default()
+        },
+        CellTexture::default(),
+    ));
+}
+
+fn system_gui(
     mut contexts: EguiContexts,
     mut cell_params: ResMut<CellParams>,
     mut gui_params: ResMut<GuiParams>,
+    simulation: Simulation,
     mut q_camera: Query<(&mut OrthographicProjection, &GlobalTransform)>,
-    q_cells: Query<Entity, With<CellPosition>>,
 ) {
+    let Simulation {
+        mut universe,
+        mut history,
+        mut timeline,
+        stats,
+        mut turmites,
+        mut selection,
+        mut pending,
+    } = simulation;
+    let queued = cell_params.playing && gui_params.edit_while_playing;
     let ctx = contexts.ctx_mut();
     ctx.set_visuals(egui::style::Visuals::light());
 
@@ -101,6 +468,16 @@ fn system_gui(
     let mut speed_slider_val = speed_slider_init;
     let scale_slider_init = scale_to_slider(camera_proj.scale);
     let mut scale_slider_val = scale_slider_init;
+    let mut topology = cell_params.topology;
+    let mut engine = cell_params.engine;
+    let mut hashlife_step_log2 = cell_params.hashlife_step_log2;
+    let timeline_last = timeline.len().saturating_sub(1);
+    let timeline_init = timeline
+        .position(universe.generation())
+        .unwrap_or(timeline_last);
+    let mut timeline_val = timeline_init;
+    let timeline_max_cells_init = timeline.max_cells() / 1000;
+    let mut timeline_max_cells_val = timeline_max_cells_init;
 
     let reset_modal = {
         let modal = Modal::new(ctx, "resel_modal");
@@ -113,7 +490,9 @@ fn system_gui(
                 modal.button(ui, "Cancel");
                 if modal.button(ui, "Clear Screen").clicked() {
                     cell_params.playing = false;
-                    clear_cells(&mut commands, &q_cells);
+                    turmites.clear();
+                    gui_params.turmite_rule_change = None;
+                    history.record(&mut universe.0, Universe::clear);
                 };
             });
         });
@@ -131,8 +510,10 @@ fn system_gui(
                 if modal.button(ui, "Random").clicked() {
                     let offset = -(gui_params.random_drag_value as isize) / 2;
                     let width = gui_params.random_drag_value as usize;
-                    clear_cells(&mut commands, &q_cells);
-                    random_cells(&mut commands, offset, offset, width, width);
+                    history.record(&mut universe.0, |universe| {
+                        universe.clear();
+                        random_cells(universe, offset, offset, width, width);
+                    });
                 };
             });
         });
@@ -147,6 +528,31 @@ fn system_gui(
                 if ui.button("Clear board").clicked() {
                     reset_modal.open();
                 }
+                let ants_walking = !turmites.is_empty();
+                if ui
+                    .add_enabled(
+                        history.can_undo() && !ants_walking,
+                        egui::Button::new("Undo"),
+                    )
+                    .on_disabled_hover_text(ANTS_NO_HISTORY)
+                    .clicked()
+                {
+                    cell_params.playing = false;
+                    history.undo(&mut universe.0);
+                    gui_params.rule_text = universe.rule().to_string();
+                }
+                if ui
+                    .add_enabled(
+                        history.can_redo() && !ants_walking,
+                        egui::Button::new("Redo"),
+                    )
+                    .on_disabled_hover_text(ANTS_NO_HISTORY)
+                    .clicked()
+                {
+                    cell_params.playing = false;
+                    history.redo(&mut universe.0);
+                    gui_params.rule_text = universe.rule().to_string();
+                }
             });
             ui.horizontal(|ui| {
                 ui.add(egui::DragValue::new(&mut gui_params.random_drag_value).suffix(" width"));
@@ -169,20 +575,417 @@ fn system_gui(
                 );
             });
             separator(ui);
+            ui.horizontal(|ui| {
+                ui.label("Rule");
+                ui.add(egui::TextEdit::singleline(&mut gui_params.rule_text).desired_width(180.0))
+                    .on_hover_text(
+                        "e.g. B3/S23, B2-a/S12, B2/S34H, B2/S/C3, WireWorld \
+                        or R5,C0,M1,S34..58,B34..45,NM",
+                    );
+                if ui.button("Apply").clicked() {
+                    match parse_rule(&gui_params.rule_text, &gui_params.loaded_rules) {
+                        Ok(new_rule) => {
+                            gui_params.rule_text = new_rule.to_string();
+                            gui_params.rule_error = None;
+                            universe.set_rule(new_rule);
+                        }
+                        Err(err) => gui_params.rule_error = Some(err.to_string()),
+                    }
+                }
+            });
+            if let Some(rule_error) = &gui_params.rule_error {
+                ui.colored_label(Color32::RED, format!("Invalid rule: {rule_error}"));
+            }
+            let states = universe.rule().states();
+            if states > 2 {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Paint");
+                    if states > MAX_PAINT_BUTTONS {
+                        ui.add(
+                            egui::DragValue::new(&mut gui_params.paint_state)
+                                .clamp_range(0..=states - 1),
+                        );
+                        return;
+                    }
+                    let colors = state_colors(universe.rule());
+                    for state in 0..states {
+                        let [r, g, b, _] = colors[state as usize].as_rgba_u8();
+                        let mut text = egui::RichText::new(state_name(universe.rule(), state));
+                        if state > 0 {
+                            text = text.color(Color32::from_rgb(r, g, b));
+                        }
+                        ui.selectable_value(&mut gui_params.paint_state, state, text);
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                ui.label("Turmite");
+                ui.add(
+                    egui::TextEdit::singleline(&mut gui_params.turmite_text).desired_width(80.0),
+                )
+                .on_hover_text("Turn on each colour, e.g. RL for Langton's Ant, RLR or LLRR");
+                if ui
+                    .button("Add ant")
+                    .on_hover_text("While ants walk, they replace the generations of the rule")
+                    .clicked()
+                {
+                    match gui_params.turmite_text.parse::<TurmiteRule>() {
+                        Ok(rule) => {
+                            let cell_rule = rule.cell_rule();
+                            if universe.rule().states() < cell_rule.states()
+                                || universe.rule().tiling() != Tiling::Square
+                            {
+                                gui_params.turmite_rule_change = Some(format!(
+                                    "Rule {} replaced by {cell_rule} for the {} colours of the ants",
+                                    universe.rule(),
+                                    rule.colours()
+                                ));
+                                gui_params.rule_text = cell_rule.to_string();
+                                universe.set_rule(cell_rule);
+                            }
+                            gui_params.turmite_text = rule.to_string();
+                            gui_params.turmite_error = None;
+                            turmites.set_rule(rule);
+                            let center = camera_transform.translation();
+                            turmites.add(Ant {
+                                position: Tiling::Square.cell_at(center.x, center.y),
+                                heading: Heading::North,
+                            });
+                        }
+                        Err(err) => gui_params.turmite_error = Some(err.to_string()),
+                    }
+                }
+                if ui
+                    .add_enabled(!turmites.is_empty(), egui::Button::new("Remove ants"))
+                    .clicked()
+                {
+                    turmites.clear();
+                    gui_params.turmite_rule_change = None;
+                }
+            });
+            if let Some(turmite_error) = &gui_params.turmite_error {
+                ui.colored_label(Color32::RED, format!("Invalid turmite: {turmite_error}"));
+            }
+            if !turmites.is_empty() {
+                ui.label("Ants are walking, the rule is paused until they are removed");
+            }
+            if let Some(rule_change) = &gui_params.turmite_rule_change {
+                ui.colored_label(Color32::DARK_BLUE, rule_change);
+            }
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Mouse");
+                for tool in EditTool::ALL {
+                    ui.selectable_value(&mut gui_params.tool, tool, tool.name());
+                }
+                if let EditTool::Shape(Shape::Rectangle | Shape::Ellipse) = gui_params.tool {
+                    ui.checkbox(&mut gui_params.filled_shapes, "Filled");
+                }
+            });
+            ui.horizontal(|ui| {
+                let selected = selection.area().is_some();
+                if ui
+                    .add_enabled(selected, egui::Button::new("Copy"))
+                    .clicked()
+                {
+                    selection.copy(&universe);
+                }
+                if ui.add_enabled(selected, egui::Button::new("Cut")).clicked() {
+                    let edit = selection.cut(&universe);
+                    apply_edit(edit, queued, &mut universe, &mut history, &mut pending);
+                }
+                if ui
+                    .add_enabled(selection.clipboard().is_some(), egui::Button::new("Paste"))
+                    .on_hover_text("Click on the board to place the copied cells")
+                    .clicked()
+                {
+                    selection.start_pasting();
+                }
+                if ui
+                    .add_enabled(selected, egui::Button::new("Delete"))
+                    .clicked()
+                {
+                    let edit = selection.delete();
+                    apply_edit(edit, queued, &mut universe, &mut history, &mut pending);
+                }
+                if ui
+                    .add_enabled(selected, egui::Button::new("Fill"))
+                    .clicked()
+                {
+                    let state = gui_params.state_to_paint(universe.rule());
+                    let edit = selection.fill(state);
+                    apply_edit(edit, queued, &mut universe, &mut history, &mut pending);
+                }
+            });
+            let transformable = (selection.area().is_some() || selection.is_pasting())
+                && universe.rule().tiling() == Tiling::Square;
+            ui.add_enabled_ui(transformable, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for transformation in Transformation::ALL {
+                        if ui.button(transformation.name()).clicked() {
+                            let edit = selection.transform(transformation);
+                            apply_edit(edit, queued, &mut universe, &mut history, &mut pending);
+                        }
+                    }
+                });
+            });
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_label("Topology")
+                    .selected_text(topology.name())
+                    .show_ui(ui, |ui| {
+                        let (width, height) = (gui_params.board_width, gui_params.board_height);
+                        for candidate in Topology::all(width as usize, height as usize) {
+                            ui.add_enabled_ui(candidate.supports(universe.rule().tiling()), |ui| {
+                                ui.selectable_value(&mut topology, candidate, candidate.name());
+                            })
+                            .response
+                            .on_disabled_hover_text(UNSUPPORTED_TOPOLOGY);
+                        }
+                    });
+            });
+            ui.add_enabled_ui(topology.size().is_some(), |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut gui_params.board_width).suffix(" width"));
+                    ui.add(egui::DragValue::new(&mut gui_params.board_height).suffix(" height"));
+                });
+            });
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_label("Engine")
+                    .selected_text(engine.name())
+                    .show_ui(ui, |ui| {
+                        for candidate in Engine::ALL {
+                            ui.add_enabled_ui(
+                                candidate.supports(universe.rule(), &topology),
+                                |ui| {
+                                    ui.selectable_value(&mut engine, candidate, candidate.name());
+                                },
+                            );
+                        }
+                    });
+            });
+            if engine == Engine::HashLife {
+                ui.add(
+                    egui::Slider::new(&mut hashlife_step_log2, 0..=hashlife::MAX_STEP_LOG2)
+                        .clamp_to_range(true)
+                        .text("Step (2^n generations)"),
+                );
+                if stats.stopped_early {
+                    ui.colored_label(
+                        Color32::RED,
+                        format!(
+                            "Paused: the pattern would grow past {} cells or out of the coordinates range",
+                            hashlife::MAX_POPULATION
+                        ),
+                    );
+                }
+            }
+            separator(ui);
             ui.horizontal(|ui| {
                 let play_text = if cell_params.playing { "Pause" } else { "Play" };
                 if ui.button(play_text).clicked() {
                     cell_params.playing = !cell_params.playing;
                 }
+                let previous_step_btn = ui.add_enabled(
+                    !cell_params.playing && timeline_init > 0,
+                    egui::Button::new("Previous Step"),
+                );
+                if previous_step_btn.clicked() {
+                    timeline_val = timeline_init - 1;
+                }
                 let next_step_btn =
                     ui.add_enabled(!cell_params.playing, egui::Button::new("Next Step"));
                 if !cell_params.playing && next_step_btn.clicked() {
                     cell_params.compute_next_generation = true;
                 };
             });
+            ui.add_enabled(
+                timeline_last > 0 && turmites.is_empty(),
+                egui::Slider::new(&mut timeline_val, 0..=timeline_last)
+                    .text("Timeline")
+                    .custom_formatter(|index, _| match timeline.get(index as usize) {
+                        Some(snapshot) => format!("gen. {}", snapshot.generation()),
+                        None => String::new(),
+                    }),
+            )
+            .on_disabled_hover_text(ANTS_NO_HISTORY);
+            ui.add(
+                egui::DragValue::new(&mut timeline_max_cells_val)
+                    .prefix("Timeline memory: ")
+                    .suffix("k cells"),
+            );
             separator(ui);
             ui.vertical(|ui| {
                 ui.checkbox(&mut gui_params.grid_enabled, "Display grid");
+                ui.checkbox(&mut gui_params.edit_while_playing, "Edit while playing")
+                    .on_hover_text("Edits are applied between two generations");
+            });
+            separator(ui);
+            ui.collapsing("Statistics", |ui| {
+                ui.label(format!("Generation: {}", stats.generation));
+                ui.label(format!("Population: {}", stats.population));
+                ui.label(format!("Births: {}", stats.births));
+                ui.label(format!("Deaths: {}", stats.deaths));
+                ui.label(match &stats.bounding_box {
+                    Some((min, max)) => format!(
+                        "Bounding box: {}x{} at ({}, {})",
+                        max.x - min.x + 1,
+                        max.y - min.y + 1,
+                        min.x,
+                        min.y
+                    ),
+                    None => "Bounding box: empty".to_string(),
+                });
+                let points: PlotPoints = stats.population_history.iter().copied().collect();
+                Plot::new("population_plot")
+                    .height(120.0)
+                    .include_y(0.0)
+                    .allow_drag(false)
+                    .allow_zoom(false)
+                    .allow_scroll(false)
+                    .allow_boxed_zoom(false)
+                    .show(ui, |plot_ui| {
+                        plot_ui.line(Line::new(points).name("Population"))
+                    });
+            });
+            separator(ui);
+            ui.collapsing("Pattern library", |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut gui_params.library_search)
+                            .hint_text("Search")
+                            .desired_width(120.),
+                    );
+                    egui::ComboBox::from_id_source("library_category")
+                        .selected_text(
+                            gui_params
+                                .library_category
+                                .map_or("All", |category| category.name()),
+                        )
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut gui_params.library_category, None, "All");
+                            for category in Category::ALL {
+                                ui.selectable_value(
+                                    &mut gui_params.library_category,
+                                    Some(category),
+                                    category.name(),
+                                );
+                            }
+                        });
+                });
+                egui::ScrollArea::vertical()
+                    .max_height(150.)
+                    .show(ui, |ui| {
+                        let category = gui_params.library_category;
+                        let search = gui_params.library_search.clone();
+                        let patterns = library::PATTERNS.iter().filter(|pattern| {
+                            category.is_none_or(|category| pattern.category == category)
+                                && pattern.matches(&search)
+                        });
+                        for pattern in patterns {
+                            let label = match pattern.period {
+                                Some(period) => format!("{} (p{period})", pattern.name),
+                                None => pattern.name.to_string(),
+                            };
+                            let selected = gui_params.library_selected == Some(pattern);
+                            if ui.selectable_label(selected, label).clicked() {
+                                gui_params.library_selected = Some(pattern);
+                            }
+                        }
+                    });
+                if let Some(pattern) = gui_params.library_selected {
+                    ui.label(match pattern.period {
+                        Some(period) => format!("{}, period {period}", pattern.category.name()),
+                        None => pattern.category.name().to_string(),
+                    });
+                    ui.label(pattern.description);
+                    if *universe.rule() != Rule::conway() {
+                        ui.colored_label(Color32::RED, "Designed for Conway's rule B3/S23");
+                    }
+                    if ui
+                        .button("Place")
+                        .on_hover_text("Click on the board to place the pattern")
+                        .clicked()
+                    {
+                        if let Some(clipboard) = Clipboard::from_pattern(pattern.pattern()) {
+                            selection.set_clipboard(clipboard);
+                            selection.start_pasting();
+                        }
+                    }
+                }
+            });
+            separator(ui);
+            ui.collapsing("Import / Export", |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut gui_params.pattern_text)
+                        .code_editor()
+                        .desired_rows(6),
+                );
+                ui.horizontal(|ui| {
+                    let import = ui
+                        .button("Import")
+                        .on_hover_text("Pattern, or Golly rule file starting with @RULE");
+                    if import.clicked() && gui_params.pattern_text.trim_start().starts_with("@RULE")
+                    {
+                        gui_params.pattern_message =
+                            match RuleTable::parse(&gui_params.pattern_text) {
+                                Ok(table) => {
+                                    let rule = Rule::from_table(table);
+                                    gui_params
+                                        .loaded_rules
+                                        .retain(|loaded| loaded.to_string() != rule.to_string());
+                                    gui_params.loaded_rules.push(rule.clone());
+                                    universe.set_rule(rule);
+                                    None
+                                }
+                                Err(err) => Some(format!("Invalid rule file: {err}")),
+                            };
+                        gui_params.rule_text = universe.rule().to_string();
+                    } else if import.clicked() {
+                        let format = Format::detect(&gui_params.pattern_text);
+                        gui_params.pattern_message = match format.read(&gui_params.pattern_text) {
+                            Ok(mut pattern) => {
+                                if gui_params.pattern_centered {
+                                    pattern = pattern.centered();
+                                }
+                                let mut message = None;
+                                history.record(&mut universe.0, |universe| {
+                                    universe.clear();
+                                    message =
+                                        load_pattern(universe, pattern, &gui_params.loaded_rules);
+                                });
+                                message
+                            }
+                            Err(err) => Some(format!("Invalid {} pattern: {err}", format.name())),
+                        };
+                        gui_params.rule_text = universe.rule().to_string();
+                    }
+                    ui.checkbox(&mut gui_params.pattern_centered, "Center");
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Export").clicked() {
+                        let mut pattern = Pattern::from_cells(universe.iter_alive());
+                        pattern.states = universe
+                            .iter_dying()
+                            .map(|(cell, state)| (cell.clone(), state))
+                            .collect();
+                        pattern.rule = Some(universe.rule().to_string());
+                        gui_params.pattern_text = gui_params.pattern_format.write(&pattern);
+                        gui_params.pattern_message = None;
+                    }
+                    egui::ComboBox::from_id_source("pattern_format")
+                        .selected_text(gui_params.pattern_format.name())
+                        .show_ui(ui, |ui| {
+                            for format in Format::ALL {
+                                ui.selectable_value(
+                                    &mut gui_params.pattern_format,
+                                    format,
+                                    format.name(),
+                                );
+                            }
+                        });
+                });
+                if let Some(pattern_message) = &gui_params.pattern_message {
+                    ui.colored_label(Color32::RED, pattern_message);
+                }
             });
             separator(ui);
             ui.vertical(|ui| {
@@ -190,8 +993,13 @@ fn system_gui(
                 let y = camera_transform.translation().y;
                 ui.label(format!("Current position: x: {x}, y: {y}"));
                 ui.add_space(5.);
-                ui.label("Click to modify grid when not playing.");
+                ui.label("Click to modify grid.");
+                ui.label("Pen, eraser, shapes and select tool: drag on the board");
+                ui.label("Right click to deselect, Escape to cancel");
+                ui.label("Ctrl+C / Ctrl+X / Ctrl+V to copy / cut / paste, Delete to clear");
+                ui.label("R / Shift+R to rotate, H / V to flip, arrows to move the selection");
                 ui.label("Keyboard arrows to move around");
+                ui.label("Ctrl+Z / Ctrl+Y to undo / redo");
             });
         });
 
@@ -202,64 +1010,519 @@ fn system_gui(
     if speed_slider_init != speed_slider_val {
         cell_params.period = Duration::from_secs_f32(slider_to_period(speed_slider_val));
     }
+    if timeline_val != timeline_init {
+        if let Some(snapshot) = timeline.get(timeline_val) {
+            cell_params.playing = false;
+            history.record(&mut universe.0, |universe| universe.restore(snapshot));
+        }
+    }
+    if timeline_max_cells_val != timeline_max_cells_init {
+        timeline.set_max_cells(timeline_max_cells_val * 1000);
+    }
+    let topology = topology.resized(
+        gui_params.board_width as usize,
+        gui_params.board_height as usize,
+    );
+    // The tiling or the size of the board may have changed
+    let topology = if topology.supports(universe.rule().tiling()) {
+        topology
+    } else {
+        Topology::Bounded {
+            width: gui_params.board_width as usize,
+            height: gui_params.board_height as usize,
+        }
+    };
+    if topology != cell_params.topology {
+        cell_params.topology = topology;
+    }
+    if !engine.supports(universe.rule(), &topology) {
+        engine = Engine::Sparse;
+    }
+    if engine != cell_params.engine {
+        cell_params.engine = engine;
+    }
+    if hashlife_step_log2 != cell_params.hashlife_step_log2 {
+        cell_params.hashlife_step_log2 = hashlife_step_log2;
+    }
 }
 
-fn system_draw_new_cells(
+/// Uploads the visible alive cells into the texture of the [`CellTexture`] sprite.
+fn system_draw_cells(
+    universe: Res<CellUniverse>,
+    mut images: ResMut<Assets<Image>>,
+    q_camera: Query<(&OrthographicProjection, &Transform), With<Camera>>,
+    mut q_texture: Query<
+        (
+            &mut CellTexture,
+            &Handle<Image>,
+            &mut Sprite,
+            &mut Transform,
+        ),
+        Without<Camera>,
+    >,
+    mut q_visibility: Query<&mut Visibility, With<CellTexture>>,
+) {
+    let (camera_proj, camera_transform) = q_camera.get_single().unwrap();
+    let (mut cell_texture, image_handle, mut sprite, mut transform) =
+        q_texture.get_single_mut().unwrap();
+    let mut visibility = q_visibility.get_single_mut().unwrap();
+    if universe.rule().tiling() != Tiling::Square {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    }
+    visibility.set_if_neq(Visibility::Inherited);
+    let area = VisibleArea::new(camera_proj, camera_transform);
+    if !universe.is_changed() && area == cell_texture.area {
+        return;
+    }
+    let Some(image) = images.get_mut(image_handle) else {
+        return;
+    };
+    let size = area.texture_size();
+    if image.texture_descriptor.size != size {
+        image.resize(size);
+    }
+    image.data.fill(0);
+    let colors: Vec<[u8; 4]> = state_colors(universe.rule())
+        .iter()
+        .map(|color| color.as_rgba_u8())
+        .collect();
+    let mut draw = |cell: &CellPosition, state: u8| {
+        if let Some(index) = area.pixel_index(cell) {
+            let pixel = &mut image.data[index * 4..index * 4 + 4];
+            // Alive cells win over dying ones sharing the same pixel
+            if state == 1 || pixel != colors[1] {
+                pixel.copy_from_slice(&colors[state as usize]);
+            }
+        }
+    };
+    // Scanning whichever is the smallest, the alive cells or the visible ones
+    if universe.population() <= area.width * area.height {
+        universe
+            .iter_dying()
+            .for_each(|(cell, state)| draw(cell, state));
+        universe.iter_alive().for_each(|cell| draw(cell, 1));
+    } else {
+        for cell in area.cells() {
+            match universe.state(&cell) {
+                0 => (),
+                state => draw(&cell, state),
+            }
+        }
+    }
+    let rect = area.sprite_rect();
+    sprite.custom_size = Some(rect.size());
+    transform.translation = rect.center().extend(0.0);
+    cell_texture.area = area;
+}
+
+/// Uploads the visible alive cells into the [`CellMesh`], as polygons.
+fn system_draw_cell_mesh(
+    universe: Res<CellUniverse>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    q_camera: Query<(&OrthographicProjection, &Transform), With<Camera>>,
+    mut q_mesh: Query<(&mut CellMesh, &Mesh2dHandle, &mut Visibility)>,
+) {
+    let (mut cell_mesh, mesh_handle, mut visibility) = q_mesh.get_single_mut().unwrap();
+    let tiling = universe.rule().tiling();
+    if tiling == Tiling::Square {
+        visibility.set_if_neq(Visibility::Hidden);
+        cell_mesh.area = None;
+        return;
+    }
+    let (camera_proj, camera_transform) = q_camera.get_single().unwrap();
+    let center = camera_transform.translation.truncate();
+    let area = Rect::from_corners(camera_proj.area.min + center, camera_proj.area.max + center);
+    if !universe.is_changed() && cell_mesh.area == Some(area) {
+        return;
+    }
+    cell_mesh.area = Some(area);
+    let Some(mesh) = meshes.get_mut(&mesh_handle.0) else {
+        return;
+    };
+    let (min, max) = tiling.cells_covering(area.min.into(), area.max.into());
+    let is_visible = |cell: &CellPosition| {
+        (min.x..=max.x).contains(&cell.x) && (min.y..=max.y).contains(&cell.y)
+    };
+    let colors: Vec<[f32; 4]> = state_colors(universe.rule())
+        .iter()
+        .map(|color| color.as_linear_rgba_f32())
+        .collect();
+    let mut positions = Vec::new();
+    let mut vertex_colors = Vec::new();
+    let mut draw = |cell: &CellPosition, state: u8| {
+        let corners = tiling.corners(cell);
+        for triangle in 1..corners.len() - 1 {
+            for (x, y) in [corners[0], corners[triangle], corners[triangle + 1]] {
+                positions.push([x, y, 0.0]);
+                vertex_colors.push(colors[state as usize]);
+            }
+        }
+    };
+    // Scanning whichever is the smallest, the alive cells or the visible ones
+    let visible_cells = ((max.x - min.x + 1) * (max.y - min.y + 1)) as usize;
+    if universe.population() <= visible_cells {
+        universe
+            .iter_dying()
+            .filter(|(cell, _)| is_visible(cell))
+            .for_each(|(cell, state)| draw(cell, state));
+        universe
+            .iter_alive()
+            .filter(|cell| is_visible(cell))
+            .for_each(|cell| draw(cell, 1));
+    } else {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let cell = CellPosition { x, y };
+                match universe.state(&cell) {
+                    0 => (),
+                    state => draw(&cell, state),
+                }
+            }
+        }
+    }
+    visibility.set_if_neq(if positions.is_empty() {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    });
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, vertex_colors);
+}
+
+/// Moves the [`AntSprite`]s onto the ants, spawning or despawning some when
+/// ants are added or removed.
+fn system_draw_ants(
     mut commands: Commands,
-    query: Query<(Entity, &CellPosition), Added<CellPosition>>,
+    turmites: Res<CellTurmites>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut q_ants: Query<(Entity, &AntSprite, &mut Transform)>,
 ) {
-    for (entity, pos) in query.iter() {
-        commands.entity(entity).insert(SpriteBundle {
-            sprite: Sprite {
-                color: CELL_COLOR,
-                custom_size: Some(Vec2::new(1.0, 1.0)),
+    let ants = turmites.ants();
+    let mut drawn = 0;
+    for (entity, sprite, mut transform) in &mut q_ants {
+        let Some(ant) = ants.get(sprite.0) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        *transform = ant_transform(ant);
+        drawn += 1;
+    }
+    for (index, ant) in ants.iter().enumerate().skip(drawn) {
+        let color = Color::hsl((index as f32 * ANT_HUE_STEP) % 360.0, 0.9, 0.45);
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: meshes
+                    .add(Mesh::from(shape::RegularPolygon::new(0.45, 3)))
+                    .into(),
+                material: materials.add(ColorMaterial::from(color)),
+                transform: ant_transform(ant),
                 ..Default::default()
             },
-            transform: Transform::from_xyz(pos.x as f32, pos.y as f32, 0.0),
-            ..Default::default()
-        });
+            AntSprite(index),
+        ));
+    }
+}
+
+/// Triangle over the cell of an ant, pointing towards its heading.
+fn ant_transform(ant: &Ant) -> Transform {
+    let (x, y) = Tiling::Square.center(&ant.position);
+    Transform::from_xyz(x, y, 1.0).with_rotation(Quat::from_rotation_z(
+        (ant.heading.angle() - 90.0).to_radians(),
+    ))
+}
+
+/// Colours of the cell states, the ones of the rule table if any, dying
+/// cells of Generations rules fading out.
+fn state_colors(rule: &Rule) -> Vec<Color> {
+    let states = rule.states();
+    (0..states)
+        .map(|state| {
+            let table_color = rule.table().and_then(|table| table.color(state));
+            match (state, table_color) {
+                (0, _) => Color::NONE,
+                (_, Some([r, g, b])) => Color::rgb_u8(r, g, b),
+                (1, None) => CELL_COLOR,
+                _ => {
+                    let fading = (state - 2) as f32 / (states - 2).max(1) as f32;
+                    let lerp = |from: f32, to: f32| from + (to - from) * fading;
+                    Color::rgb(
+                        lerp(DYING_COLOR.r(), DYING_FADED_COLOR.r()),
+                        lerp(DYING_COLOR.g(), DYING_FADED_COLOR.g()),
+                        lerp(DYING_COLOR.b(), DYING_FADED_COLOR.b()),
+                    )
+                }
+            }
+        })
+        .collect()
+}
+
+/// Name of a state in the paint palette.
+fn state_name(rule: &Rule, state: u8) -> String {
+    match (
+        state,
+        rule.table().and_then(|table| table.state_name(state)),
+    ) {
+        (_, Some(name)) => name.to_string(),
+        (0, None) => "Dead".to_string(),
+        (1, None) => "Alive".to_string(),
+        (_, None) if rule.table().is_none() => format!("Dying {}", state - 1),
+        (_, None) => format!("State {state}"),
     }
 }
 
 fn system_mouse_click(
-    mut commands: Commands,
+    mut contexts: EguiContexts,
     cell_params: Res<CellParams>,
+    gui_params: Res<GuiParams>,
+    mut editing: CellEditing,
     q_windows: Query<&Window, With<PrimaryWindow>>,
     q_camera: Query<(&Camera, &GlobalTransform)>,
-    q_cellpos: Query<(Entity, &CellPosition)>,
     buttons: Res<Input<MouseButton>>,
 ) {
-    if cell_params.playing || !buttons.just_released(MouseButton::Left) {
+    let queued = cell_params.playing && gui_params.edit_while_playing;
+    let (camera, camera_transform) = q_camera.single();
+    let window = q_windows.single();
+    let tiling = editing.universe.rule().tiling();
+    let cell = cursor_cell(window, camera, camera_transform, tiling);
+    let released = !buttons.pressed(MouseButton::Left);
+    let state = match gui_params.tool {
+        EditTool::Eraser => 0,
+        _ => gui_params.state_to_paint(editing.universe.rule()),
+    };
+    if editing.drawing.is_drawing() {
+        match (gui_params.tool, &cell) {
+            (EditTool::Pen | EditTool::Eraser, Some(cell)) => {
+                let cells = editing.drawing.continue_stroke(cell);
+                editing.apply(Some(drawing::paint(cells, state)), queued);
+            }
+            (EditTool::Shape(shape), Some(cell)) if released => {
+                let cells = editing
+                    .drawing
+                    .end_shape(shape, gui_params.filled_shapes, cell);
+                editing.apply(cells.map(|cells| drawing::paint(cells, state)), queued);
+            }
+            _ => (),
+        }
+        if released {
+            editing.drawing.cancel_shape();
+            if editing.drawing.end_stroke() {
+                editing.end_group(queued);
+            }
+        }
         return;
     }
-    let Some(cursor_position) = q_windows.single().cursor_position() else {
+    if editing.selection.is_dragging() {
+        if let Some(cell) = &cell {
+            editing.selection.drag_to(cell);
+        }
+        if released {
+            editing.selection.end_drag();
+        }
         return;
-    };
-    let (camera, camera_transform) = q_camera.single();
-    let Some(target_pos) = camera
-        .viewport_to_world(camera_transform, cursor_position)
-        .map(|ray| ray.origin.truncate().round())
-    else {
+    }
+    let Some(cell) = cell else {
         return;
     };
-    debug!("Clicked on: {target_pos}");
-    let new_cell = CellPosition {
-        x: target_pos.x as isize,
-        y: target_pos.y as isize,
-    };
-    for (entity, cell_pos) in q_cellpos.iter() {
-        if cell_pos == &new_cell {
-            commands.entity(entity).despawn();
-            return;
+    if is_pointer_over_window(contexts.ctx_mut()) {
+        return;
+    }
+    let locked = cell_params.playing && !gui_params.edit_while_playing;
+    if editing.selection.is_pasting() {
+        if buttons.just_pressed(MouseButton::Right) {
+            editing.selection.deselect();
+        } else if !locked && buttons.just_released(MouseButton::Left) {
+            let edit = editing.selection.paste(&cell);
+            editing.apply(edit, queued);
+        }
+        return;
+    }
+    match gui_params.tool {
+        EditTool::Select => {
+            if buttons.just_pressed(MouseButton::Left) {
+                editing.selection.start_drag(cell);
+            } else if buttons.just_pressed(MouseButton::Right) {
+                editing.selection.deselect();
+            }
+        }
+        _ if locked => (),
+        EditTool::Pen | EditTool::Eraser => {
+            if buttons.just_pressed(MouseButton::Left) && cell_params.topology.contains(&cell) {
+                editing.start_group();
+                let cells = editing.drawing.start_stroke(cell);
+                editing.apply(Some(drawing::paint(cells, state)), queued);
+            }
+        }
+        EditTool::Shape(_) => {
+            if buttons.just_pressed(MouseButton::Left) {
+                editing.drawing.start_shape(cell);
+            }
+        }
+        EditTool::FloodFill => {
+            if !buttons.just_released(MouseButton::Left) {
+                return;
+            }
+            let Some((min, max)) = visible_cells(window, camera, camera_transform, tiling) else {
+                return;
+            };
+            let edit: Edit = Box::new(move |universe: &mut Universe| {
+                universe.flood_fill(cell, state, &min, &max)
+            });
+            editing.apply(Some(edit), queued);
+        }
+        EditTool::Toggle => {
+            if !buttons.just_released(MouseButton::Left) {
+                return;
+            }
+            debug!("Clicked on: {cell:?}");
+            if !cell_params.topology.contains(&cell) {
+                return;
+            }
+            let multi_state = editing.universe.rule().states() > 2;
+            let edit: Edit = Box::new(move |universe: &mut Universe| {
+                if !multi_state {
+                    universe.toggle(cell);
+                } else if universe.state(&cell) == state {
+                    universe.set_state(cell, 0);
+                } else {
+                    universe.set_state(cell, state);
+                }
+            });
+            editing.apply(Some(edit), queued);
         }
     }
-    commands.spawn(new_cell);
+}
+
+/// Bottom left and top right corners of the box of cells covering the window.
+fn visible_cells(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    tiling: Tiling,
+) -> Option<(CellPosition, CellPosition)> {
+    let to_world = |x: f32, y: f32| {
+        camera
+            .viewport_to_world(camera_transform, Vec2::new(x, y))
+            .map(|ray| ray.origin.truncate())
+    };
+    let (corner, opposite) = (
+        to_world(0.0, 0.0)?,
+        to_world(window.width(), window.height())?,
+    );
+    Some(tiling.cells_covering(
+        (corner.x.min(opposite.x), corner.y.min(opposite.y)),
+        (corner.x.max(opposite.x), corner.y.max(opposite.y)),
+    ))
+}
+
+/// Cell under the mouse cursor, if it is within the window.
+fn cursor_cell(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    tiling: Tiling,
+) -> Option<CellPosition> {
+    let cursor_position = window.cursor_position()?;
+    let target_pos = camera
+        .viewport_to_world(camera_transform, cursor_position)?
+        .origin
+        .truncate();
+    Some(tiling.cell_at(target_pos.x, target_pos.y))
 }
 
 fn system_keyboard_input(
+    mut contexts: EguiContexts,
     keys: Res<Input<KeyCode>>,
+    mut cell_params: ResMut<CellParams>,
+    mut gui_params: ResMut<GuiParams>,
+    turmites: Res<CellTurmites>,
+    mut editing: CellEditing,
     mut q_camera_transform: Query<&mut Transform, With<Camera>>,
 ) {
+    let queued = cell_params.playing && gui_params.edit_while_playing;
+    let typing = contexts.ctx_mut().wants_keyboard_input();
+    let ctrl_pressed = keys.any_pressed([
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::SuperLeft,
+        KeyCode::SuperRight,
+    ]);
+    let shift_pressed = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if ctrl_pressed && !typing {
+        // The ants are not part of the history, see `ANTS_NO_HISTORY`.
+        let history_enabled = turmites.is_empty();
+        if history_enabled && keys.just_pressed(KeyCode::Z) && !shift_pressed {
+            cell_params.playing = false;
+            editing.history.undo(&mut editing.universe.0);
+            gui_params.rule_text = editing.universe.rule().to_string();
+        } else if history_enabled
+            && (keys.just_pressed(KeyCode::Y) || (keys.just_pressed(KeyCode::Z) && shift_pressed))
+        {
+            cell_params.playing = false;
+            editing.history.redo(&mut editing.universe.0);
+            gui_params.rule_text = editing.universe.rule().to_string();
+        } else if keys.just_pressed(KeyCode::C) {
+            editing.selection.copy(&editing.universe);
+        } else if keys.just_pressed(KeyCode::X) {
+            let edit = editing.selection.cut(&editing.universe);
+            editing.apply(edit, queued);
+        } else if keys.just_pressed(KeyCode::V) {
+            editing.selection.start_pasting();
+        }
+    } else if !typing {
+        let transformation = if keys.just_pressed(KeyCode::R) && shift_pressed {
+            Some(Transformation::RotateCounterclockwise)
+        } else if keys.just_pressed(KeyCode::R) {
+            Some(Transformation::RotateClockwise)
+        } else if keys.just_pressed(KeyCode::H) {
+            Some(Transformation::FlipHorizontal)
+        } else if keys.just_pressed(KeyCode::V) {
+            Some(Transformation::FlipVertical)
+        } else {
+            None
+        };
+        if keys.any_just_pressed([KeyCode::Delete, KeyCode::Back]) {
+            let edit = editing.selection.delete();
+            editing.apply(edit, queued);
+        } else if keys.just_pressed(KeyCode::Escape) {
+            editing.selection.deselect();
+            editing.drawing.cancel_shape();
+        } else if let Some(transformation) =
+            transformation.filter(|_| editing.universe.rule().tiling() == Tiling::Square)
+        {
+            let edit = editing.selection.transform(transformation);
+            editing.apply(edit, queued);
+        }
+    }
+
+    // Arrows move the selected cells with the selection tool, the view otherwise
+    let moving_selection = gui_params.tool == EditTool::Select
+        && editing.selection.area().is_some()
+        && !editing.selection.is_pasting();
+    if moving_selection {
+        if typing {
+            return;
+        }
+        let (mut x, mut y) = (0, 0);
+        if keys.just_pressed(KeyCode::Left) {
+            x += -1;
+        }
+        if keys.just_pressed(KeyCode::Right) {
+            x += 1;
+        }
+        if keys.just_pressed(KeyCode::Up) {
+            y += 1;
+        }
+        if keys.just_pressed(KeyCode::Down) {
+            y += -1;
+        }
+        if (x, y) != (0, 0) {
+            let edit = editing.selection.nudge(x, y);
+            editing.apply(edit, queued);
+        }
+        return;
+    }
     let (mut x, mut y) = (0, 0);
     if keys.pressed(KeyCode::Left) {
         x += -1;
@@ -279,9 +1542,12 @@ fn system_keyboard_input(
 
 fn system_draw_grid(
     mut contexts: EguiContexts,
+    universe: Res<CellUniverse>,
     q_camera: Query<(&Camera, &OrthographicProjection, &GlobalTransform)>,
 ) {
     const LINE_COLOR: Color32 = Color32::BLACK;
+    /// Above this number of visible cells, polygon outlines are not drawn.
+    const MAX_OUTLINED_CELLS: isize = 20_000;
     let (camera, camera_proj, camera_transform) = q_camera.get_single().unwrap();
     let ctx = contexts.ctx_mut();
     let transparent_frame = egui::containers::Frame {
@@ -324,6 +1590,34 @@ fn system_draw_grid(
                 visible_bottom_right.x.round() as isize,
                 visible_bottom_right.y.round() as isize,
             );
+            let tiling = universe.rule().tiling();
+            if tiling != Tiling::Square {
+                let (min, max) = tiling.cells_covering(
+                    (visible_top_left.x, visible_bottom_right.y),
+                    (visible_bottom_right.x, visible_top_left.y),
+                );
+                if (max.x - min.x + 1) * (max.y - min.y + 1) > MAX_OUTLINED_CELLS {
+                    return;
+                }
+                let stroke = egui::Stroke {
+                    width: line_width,
+                    color: LINE_COLOR,
+                };
+                for y in min.y..=max.y {
+                    for x in min.x..=max.x {
+                        let points = tiling
+                            .corners(&CellPosition { x, y })
+                            .into_iter()
+                            .filter_map(|(x, y)| {
+                                camera.world_to_viewport(camera_transform, Vec3 { x, y, z: 0.0 })
+                            })
+                            .map(|pos| egui::Pos2::new(pos.x, pos.y))
+                            .collect();
+                        painter.add(egui::Shape::closed_line(points, stroke));
+                    }
+                }
+                return;
+            }
             for x in x_min..=x_max {
                 let start = camera
                     .world_to_viewport(
@@ -389,26 +1683,215 @@ fn system_draw_grid(
         });
 }
 
-fn clear_cells(commands: &mut Commands, q_cells: &Query<Entity, With<CellPosition>>) {
-    for entity in q_cells.iter() {
-        commands.entity(entity).despawn();
+fn system_draw_board_border(
+    mut contexts: EguiContexts,
+    universe: Res<CellUniverse>,
+    cell_params: Res<CellParams>,
+    q_camera: Query<(&Camera, &GlobalTransform)>,
+) {
+    const BORDER_COLOR: Color32 = Color32::RED;
+    const BORDER_WIDTH: f32 = 2.0;
+    let Some((min, max)) = cell_params.topology.bounds() else {
+        return;
+    };
+    let (camera, camera_transform) = q_camera.get_single().unwrap();
+    let to_viewport = |x: f32, y: f32| {
+        camera
+            .world_to_viewport(camera_transform, Vec3 { x, y, z: 0.0 })
+            .map(|pos| egui::Pos2::new(pos.x, pos.y))
+    };
+    let Some(corners) = universe
+        .rule()
+        .tiling()
+        .board_corners(&min, &max)
+        .into_iter()
+        .map(|(x, y)| to_viewport(x, y))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return;
+    };
+    contexts
+        .ctx_mut()
+        .layer_painter(egui::LayerId::background())
+        .add(egui::Shape::closed_line(
+            corners,
+            egui::Stroke {
+                width: BORDER_WIDTH,
+                color: BORDER_COLOR,
+            },
+        ));
+}
+
+/// Outlines the selected cells, and shows the cells being pasted or the
+/// shape being drawn under the cursor.
+fn system_draw_edit_preview(
+    mut contexts: EguiContexts,
+    universe: Res<CellUniverse>,
+    selection: Res<Selection>,
+    drawing: Res<Drawing>,
+    gui_params: Res<GuiParams>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform)>,
+) {
+    const SELECTION_COLOR: Color32 = Color32::from_rgb(0, 100, 255);
+    const SELECTION_FILL: Color32 = Color32::from_rgba_premultiplied(0, 20, 50, 50);
+    const SELECTION_WIDTH: f32 = 2.0;
+    const GHOST_ALPHA: f32 = 0.5;
+    /// Above this number of cells being pasted or drawn, they are not shown.
+    const MAX_GHOST_CELLS: usize = 20_000;
+    let (camera, camera_transform) = q_camera.get_single().unwrap();
+    let tiling = universe.rule().tiling();
+    let to_viewport = |(x, y): (f32, f32)| {
+        camera
+            .world_to_viewport(camera_transform, Vec3 { x, y, z: 0.0 })
+            .map(|pos| egui::Pos2::new(pos.x, pos.y))
+    };
+    let outline = |min: &CellPosition, max: &CellPosition| {
+        tiling
+            .board_corners(min, max)
+            .into_iter()
+            .map(to_viewport)
+            .collect::<Option<Vec<_>>>()
+    };
+    let stroke = egui::Stroke {
+        width: SELECTION_WIDTH,
+        color: SELECTION_COLOR,
+    };
+    let painter = contexts
+        .ctx_mut()
+        .layer_painter(egui::LayerId::background());
+
+    if let Some(corners) = selection.area().and_then(|(min, max)| outline(min, max)) {
+        painter.add(egui::Shape::convex_polygon(corners, SELECTION_FILL, stroke));
+    }
+    let Some(cursor) = cursor_cell(q_windows.single(), camera, camera_transform, tiling) else {
+        return;
+    };
+    let ghost: Vec<(CellPosition, u8)> = match (gui_params.tool, drawing.start()) {
+        (EditTool::Shape(shape), Some(start)) => {
+            let state = gui_params.state_to_paint(universe.rule());
+            shape
+                .cells(start, &cursor, gui_params.filled_shapes)
+                .into_iter()
+                .map(|cell| (cell, state))
+                .collect()
+        }
+        _ => {
+            let (Some(clipboard), Some(offset)) = (
+                selection.clipboard().filter(|_| selection.is_pasting()),
+                selection.paste_offset(&cursor),
+            ) else {
+                return;
+            };
+            let max = CellPosition {
+                x: offset.x + clipboard.width - 1,
+                y: offset.y + clipboard.height - 1,
+            };
+            if let Some(corners) = outline(&offset, &max) {
+                painter.add(egui::Shape::closed_line(corners, stroke));
+            }
+            let pattern = &clipboard.pattern;
+            let alive = pattern.cells.iter().map(|cell| (cell, 1));
+            let states = pattern.states.iter().map(|(cell, state)| (cell, *state));
+            alive
+                .chain(states)
+                .take(MAX_GHOST_CELLS + 1)
+                .map(|(cell, state)| {
+                    let cell = CellPosition {
+                        x: cell.x + offset.x,
+                        y: cell.y + offset.y,
+                    };
+                    (cell, state)
+                })
+                .collect()
+        }
+    };
+    if ghost.len() > MAX_GHOST_CELLS {
+        return;
+    }
+    let colors = state_colors(universe.rule());
+    for (cell, state) in ghost {
+        let Some(corners) = tiling
+            .corners(&cell)
+            .into_iter()
+            .map(to_viewport)
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        let [r, g, b, _] = match state {
+            0 => BACKGROUND_COLOR,
+            _ => colors.get(state as usize).copied().unwrap_or(CELL_COLOR),
+        }
+        .as_rgba_u8();
+        let color = Color32::from_rgba_unmultiplied(r, g, b, (GHOST_ALPHA * 255.0) as u8);
+        painter.add(egui::Shape::convex_polygon(
+            corners,
+            color,
+            egui::Stroke::NONE,
+        ));
+    }
+}
+
+/// Adds the cells of the pattern, and applies its rule if supported.
+///
+/// Returns a warning message if the rule of the pattern could not be applied.
+fn load_pattern(
+    universe: &mut Universe,
+    pattern: Pattern,
+    loaded_rules: &[Rule],
+) -> Option<String> {
+    // The rule comes first, as it tells which states the cells may have
+    let message = pattern.rule.as_ref().and_then(|pattern_rule| {
+        match parse_rule(pattern_rule, loaded_rules) {
+            Ok(new_rule) => {
+                universe.set_rule(new_rule);
+                None
+            }
+            Err(err) => Some(format!("Rule {pattern_rule} ignored: {err}")),
+        }
+    });
+    universe.paste(&pattern, &CellPosition { x: 0, y: 0 });
+    message
+}
+
+/// Parses a rule, or finds the loaded rule file of the same name.
+fn parse_rule(text: &str, loaded_rules: &[Rule]) -> Result<Rule, RuleParseError> {
+    let text = text.trim();
+    match loaded_rules
+        .iter()
+        .find(|rule| rule.to_string().eq_ignore_ascii_case(text))
+    {
+        Some(rule) => Ok(rule.clone()),
+        None => text.parse(),
     }
 }
 
-fn random_cells(commands: &mut Commands, x: isize, y: isize, width: usize, height: usize) {
+fn random_cells(universe: &mut Universe, x: isize, y: isize, width: usize, height: usize) {
     let mut rng = rand::thread_rng();
     for coord_x in x..(x + width as isize) {
         for coord_y in y..(y + height as isize) {
             if rng.gen::<bool>() {
-                commands.spawn(CellPosition {
-                    x: coord_x,
-                    y: coord_y,
-                });
+                universe.set(
+                    CellPosition {
+                        x: coord_x,
+                        y: coord_y,
+                    },
+                    true,
+                );
             }
         }
     }
 }
 
+/// Whether the pointer is above an egui window rather than the board, which
+/// is only covered by background layers.
+fn is_pointer_over_window(ctx: &egui::Context) -> bool {
+    ctx.pointer_interact_pos()
+        .and_then(|pos| ctx.layer_id_at(pos))
+        .is_some_and(|layer| layer.order != egui::Order::Background)
+}
+
 fn period_to_slider(period: f32) -> f32 {
     (100.0 - 99.0 * (period - PERIOD_MIN) / (PERIOD_MAX - PERIOD_MIN)).clamp(1.0, 100.0)
 }
@@ -0,0 +1,438 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! HashLife algorithm, a memoised quadtree able to compute `2^k` generations
+//! at once.
+//!
+//! See <https://conwaylife.com/wiki/HashLife>. Nodes are hash-consed in an
+//! arena which is kept between steps, so that results computed for a given
+//! part of a pattern can be reused later on.
+
+use std::collections::HashMap;
+
+use crate::{rule::Rule, tiling::Tiling, universe::CellPosition};
+
+type NodeId = u32;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+/// Past this number of nodes, the arena is emptied before loading a new pattern.
+const MAX_NODES: usize = 1 << 22;
+/// Avoids overflowing coordinates when expanding the universe.
+const MAX_LEVEL: u8 = 60;
+/// Largest jump computed by [`HashLife::step`], `2^MAX_STEP_LOG2` generations.
+pub const MAX_STEP_LOG2: u8 = MAX_LEVEL - 3;
+/// Largest population reached by [`HashLife::step`], so that the cells can
+/// still be listed.
+pub const MAX_POPULATION: u64 = 1 << 22;
+
+/// Quadrants of a node, in the order: low x & low y, high x & low y,
+/// low x & high y, high x & high y.
+#[derive(Clone, Copy)]
+struct Node {
+    level: u8,
+    population: u64,
+    children: [NodeId; 4],
+}
+
+pub struct HashLife {
+    rule: Rule,
+    nodes: Vec<Node>,
+    interned: HashMap<[NodeId; 4], NodeId>,
+    empty: Vec<NodeId>,
+    memo: HashMap<(NodeId, u8), NodeId>,
+    root: NodeId,
+}
+
+impl Default for HashLife {
+    fn default() -> Self {
+        Self::new(Rule::default())
+    }
+}
+
+impl HashLife {
+    pub fn new(rule: Rule) -> Self {
+        let leaf = |population| Node {
+            level: 0,
+            population,
+            children: [DEAD; 4],
+        };
+        let mut hashlife = Self {
+            rule,
+            nodes: vec![leaf(0), leaf(1)],
+            interned: HashMap::new(),
+            empty: vec![DEAD],
+            memo: HashMap::new(),
+            root: DEAD,
+        };
+        hashlife.root = hashlife.empty_node(2);
+        hashlife
+    }
+
+    /// Changes the rule, discarding previously memoised results if needed.
+    pub fn set_rule(&mut self, rule: &Rule) {
+        if &self.rule != rule {
+            self.rule = rule.clone();
+            self.memo.clear();
+        }
+    }
+
+    /// Replaces the current pattern with the provided cells.
+    pub fn load<'a>(&mut self, cells: impl IntoIterator<Item = &'a CellPosition>) {
+        if self.nodes.len() > MAX_NODES {
+            *self = Self::new(self.rule.clone());
+        }
+        let mut cells: Vec<(i64, i64)> = cells
+            .into_iter()
+            .map(|cell| (cell.x as i64, cell.y as i64))
+            .collect();
+        let extent = cells
+            .iter()
+            .map(|&(x, y)| x.max(y).max(-x - 1).max(-y - 1))
+            .max()
+            .unwrap_or(0);
+        let mut level = 2;
+        while (1_i64 << (level - 1)) <= extent {
+            level += 1;
+        }
+        let corner = -(1_i64 << (level - 1));
+        self.root = self.build(&mut cells, corner, corner, level);
+    }
+
+    pub fn population(&self) -> u64 {
+        self.nodes[self.root as usize].population
+    }
+
+    pub fn cells(&self) -> Vec<CellPosition> {
+        let mut cells = Vec::with_capacity(self.population() as usize);
+        let corner = -(1_i64 << (self.level(self.root) - 1));
+        self.collect(self.root, corner, corner, &mut cells);
+        cells
+    }
+
+    /// Advances the pattern by `2^step_log2` generations.
+    ///
+    /// Returns false, leaving the pattern unchanged, if it could grow out of
+    /// the coordinates range or past [`MAX_POPULATION`] cells.
+    ///
+    /// # Panics
+    ///
+    /// If `step_log2` is above [`MAX_STEP_LOG2`].
+    pub fn step(&mut self, step_log2: u8) -> bool {
+        assert!(step_log2 <= MAX_STEP_LOG2, "step too large for HashLife");
+        if self.nodes.len() > MAX_NODES {
+            self.compact();
+        }
+        let previous = self.root;
+        while (self.level(self.root) < step_log2 + 2 || !self.is_padded(self.root))
+            && self.level(self.root) < MAX_LEVEL
+        {
+            self.root = self.expand(self.root);
+        }
+        if !self.is_padded(self.root) {
+            return false;
+        }
+        // The pattern may grow by 2^step_log2 cells in every direction.
+        self.root = self.expand(self.root);
+        self.root = self.successor(self.root, step_log2);
+        if self.population() > MAX_POPULATION {
+            self.root = previous;
+            return false;
+        }
+        true
+    }
+
+    /// Copies the current pattern into a new arena, forgetting the nodes and
+    /// memoised results of the previous generations.
+    fn compact(&mut self) {
+        let mut compacted = Self::new(self.rule.clone());
+        let mut copied = HashMap::new();
+        compacted.root = compacted.copy(self, self.root, &mut copied);
+        *self = compacted;
+    }
+
+    fn copy(&mut self, from: &Self, node: NodeId, copied: &mut HashMap<NodeId, NodeId>) -> NodeId {
+        // Leaves are the first nodes of every arena
+        if from.level(node) == 0 {
+            return node;
+        }
+        if let Some(id) = copied.get(&node) {
+            return *id;
+        }
+        let children = from
+            .children(node)
+            .map(|child| self.copy(from, child, copied));
+        let id = self.join(children);
+        copied.insert(node, id);
+        id
+    }
+
+    fn level(&self, node: NodeId) -> u8 {
+        self.nodes[node as usize].level
+    }
+
+    fn children(&self, node: NodeId) -> [NodeId; 4] {
+        self.nodes[node as usize].children
+    }
+
+    fn join(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(node) = self.interned.get(&children) {
+            return *node;
+        }
+        let node = Node {
+            level: self.level(children[0]) + 1,
+            population: children
+                .iter()
+                .map(|child| self.nodes[*child as usize].population)
+                .sum(),
+            children,
+        };
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(node);
+        self.interned.insert(children, id);
+        id
+    }
+
+    fn empty_node(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let below = *self.empty.last().unwrap();
+            let node = self.join([below; 4]);
+            self.empty.push(node);
+        }
+        self.empty[level as usize]
+    }
+
+    fn is_empty(&self, node: NodeId) -> bool {
+        self.nodes[node as usize].population == 0
+    }
+
+    /// Whether only the central quarter of the node is populated.
+    fn is_padded(&self, node: NodeId) -> bool {
+        let [nw, ne, sw, se] = self.children(node);
+        let only_populated = |quadrant: NodeId, allowed: usize| {
+            self.children(quadrant)
+                .iter()
+                .enumerate()
+                .all(|(idx, child)| idx == allowed || self.is_empty(*child))
+        };
+        only_populated(nw, 3)
+            && only_populated(ne, 2)
+            && only_populated(sw, 1)
+            && only_populated(se, 0)
+    }
+
+    /// Doubles the size of the node, keeping its content at the center.
+    fn expand(&mut self, node: NodeId) -> NodeId {
+        let empty = self.empty_node(self.level(node) - 1);
+        let [nw, ne, sw, se] = self.children(node);
+        let children = [
+            self.join([empty, empty, empty, nw]),
+            self.join([empty, empty, ne, empty]),
+            self.join([empty, sw, empty, empty]),
+            self.join([se, empty, empty, empty]),
+        ];
+        self.join(children)
+    }
+
+    fn center(&mut self, node: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.children(node);
+        self.join([
+            self.children(nw)[3],
+            self.children(ne)[2],
+            self.children(sw)[1],
+            self.children(se)[0],
+        ])
+    }
+
+    /// Center of the node, advanced by `2^min(step_log2, level - 2)` generations.
+    fn successor(&mut self, node: NodeId, step_log2: u8) -> NodeId {
+        let level = self.level(node);
+        if self.is_empty(node) {
+            return self.empty_node(level - 1);
+        }
+        let step_log2 = step_log2.min(level - 2);
+        if let Some(result) = self.memo.get(&(node, step_log2)) {
+            return *result;
+        }
+        let result = if level == 2 {
+            self.base_case(node)
+        } else {
+            let [nw, ne, sw, se] = self.children(node);
+            let [_, nw_ne, nw_sw, nw_se] = self.children(nw);
+            let [ne_nw, _, ne_sw, ne_se] = self.children(ne);
+            let [sw_nw, sw_ne, _, sw_se] = self.children(sw);
+            let [se_nw, se_ne, se_sw, _] = self.children(se);
+            let sub_nodes = [
+                nw,
+                self.join([nw_ne, ne_nw, nw_se, ne_sw]),
+                ne,
+                self.join([nw_sw, nw_se, sw_nw, sw_ne]),
+                self.join([nw_se, ne_sw, sw_ne, se_nw]),
+                self.join([ne_sw, ne_se, se_nw, se_ne]),
+                sw,
+                self.join([sw_ne, se_nw, sw_se, se_sw]),
+                se,
+            ];
+            let mut c = [DEAD; 9];
+            for (idx, sub_node) in sub_nodes.into_iter().enumerate() {
+                c[idx] = self.successor(sub_node, step_log2);
+            }
+            let quadrants = [
+                self.join([c[0], c[1], c[3], c[4]]),
+                self.join([c[1], c[2], c[4], c[5]]),
+                self.join([c[3], c[4], c[6], c[7]]),
+                self.join([c[4], c[5], c[7], c[8]]),
+            ];
+            let mut result = [DEAD; 4];
+            for (idx, quadrant) in quadrants.into_iter().enumerate() {
+                result[idx] = if step_log2 == level - 2 {
+                    self.successor(quadrant, step_log2)
+                } else {
+                    self.center(quadrant)
+                };
+            }
+            self.join(result)
+        };
+        self.memo.insert((node, step_log2), result);
+        result
+    }
+
+    /// Next generation of the 2×2 center of a 4×4 node.
+    fn base_case(&mut self, node: NodeId) -> NodeId {
+        let mut grid = [[false; 4]; 4];
+        for (quadrant_idx, quadrant) in self.children(node).into_iter().enumerate() {
+            for (leaf_idx, leaf) in self.children(quadrant).into_iter().enumerate() {
+                let x = 2 * (quadrant_idx % 2) + leaf_idx % 2;
+                let y = 2 * (quadrant_idx / 2) + leaf_idx / 2;
+                grid[y][x] = leaf == ALIVE;
+            }
+        }
+        let mut result = [DEAD; 4];
+        for (idx, (x, y)) in [(1usize, 1usize), (2, 1), (1, 2), (2, 2)]
+            .into_iter()
+            .enumerate()
+        {
+            let neighbours_count = Tiling::Square
+                .neighbours(&CellPosition {
+                    x: x as isize,
+                    y: y as isize,
+                })
+                .iter()
+                .enumerate()
+                .filter(|(_, (dx, dy))| {
+                    grid[y.wrapping_add_signed(*dy)][x.wrapping_add_signed(*dx)]
+                })
+                .map(|(index, _)| self.rule.neighbour_weight(index))
+                .sum();
+            let alive = if grid[y][x] {
+                self.rule.is_survival(neighbours_count)
+            } else {
+                self.rule.is_birth(neighbours_count)
+            };
+            result[idx] = if alive { ALIVE } else { DEAD };
+        }
+        self.join(result)
+    }
+
+    /// Builds the node whose lowest corner is at the provided coordinates.
+    fn build(&mut self, cells: &mut [(i64, i64)], x: i64, y: i64, level: u8) -> NodeId {
+        if cells.is_empty() {
+            return self.empty_node(level);
+        }
+        if level == 0 {
+            return ALIVE;
+        }
+        let half = 1_i64 << (level - 1);
+        let split_y = partition(cells, |cell| cell.1 < y + half);
+        let (low_y, high_y) = cells.split_at_mut(split_y);
+        let split_low = partition(low_y, |cell| cell.0 < x + half);
+        let split_high = partition(high_y, |cell| cell.0 < x + half);
+        let (nw, ne) = low_y.split_at_mut(split_low);
+        let (sw, se) = high_y.split_at_mut(split_high);
+        let children = [
+            self.build(nw, x, y, level - 1),
+            self.build(ne, x + half, y, level - 1),
+            self.build(sw, x, y + half, level - 1),
+            self.build(se, x + half, y + half, level - 1),
+        ];
+        self.join(children)
+    }
+
+    fn collect(&self, node: NodeId, x: i64, y: i64, cells: &mut Vec<CellPosition>) {
+        if self.is_empty(node) {
+            return;
+        }
+        let level = self.level(node);
+        if level == 0 {
+            cells.push(CellPosition {
+                x: x as isize,
+                y: y as isize,
+            });
+            return;
+        }
+        let half = 1_i64 << (level - 1);
+        let [nw, ne, sw, se] = self.children(node);
+        self.collect(nw, x, y, cells);
+        self.collect(ne, x + half, y, cells);
+        self.collect(sw, x, y + half, cells);
+        self.collect(se, x + half, y + half, cells);
+    }
+}
+
+/// Moves the elements matching the predicate at the beginning of the slice,
+/// and returns their count.
+fn partition<T>(slice: &mut [T], predicate: impl Fn(&T) -> bool) -> usize {
+    let mut split = 0;
+    for idx in 0..slice.len() {
+        if predicate(&slice[idx]) {
+            slice.swap(split, idx);
+            split += 1;
+        }
+    }
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compacting_keeps_the_pattern() {
+        // R-pentomino
+        let cells =
+            [(0, 0), (1, 0), (-1, -1), (0, -1), (0, -2)].map(|(x, y)| CellPosition { x, y });
+        let mut hashlife = HashLife::new(Rule::conway());
+        hashlife.load(&cells);
+        assert!(hashlife.step(6));
+        let (population, mut before) = (hashlife.population(), hashlife.cells());
+        let nodes = hashlife.nodes.len();
+        hashlife.compact();
+        assert!(hashlife.nodes.len() < nodes);
+        assert_eq!(hashlife.population(), population);
+        let mut after = hashlife.cells();
+        before.sort();
+        after.sort();
+        assert_eq!(after, before);
+    }
+}
@@ -0,0 +1,540 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Undo and redo of the modifications of a [`Universe`], and record of its
+//! past generations.
+
+use std::collections::VecDeque;
+
+use crate::{
+    rule::Rule,
+    universe::{CellPosition, Snapshot, Universe},
+};
+
+/// Cells whose state changed between two states of a universe.
+///
+/// Toggling those cells goes from one state to the other, so the same change
+/// is used to undo and redo.
+#[derive(Clone, Debug)]
+pub struct Change {
+    toggled: Vec<CellPosition>,
+    deaths: usize,
+    /// Cells whose dying state changed, with their states before and after.
+    decayed: Vec<(CellPosition, u8, u8)>,
+    generation_before: u64,
+    generation_after: u64,
+    /// Rules before and after, if the rule changed.
+    rules: Option<(Rule, Rule)>,
+}
+
+impl Change {
+    pub fn between(before: &Snapshot, after: &Universe) -> Self {
+        let mut toggled: Vec<CellPosition> = before
+            .iter_alive()
+            .filter(|cell| !after.get(cell))
+            .cloned()
+            .collect();
+        let deaths = toggled.len();
+        toggled.extend(after.iter_alive().filter(|cell| !before.get(cell)).cloned());
+        let mut decayed: Vec<(CellPosition, u8, u8)> = before
+            .iter_dying()
+            .filter(|(cell, state)| after.state(cell) != *state)
+            .map(|(cell, state)| (cell.clone(), state, dying_state(after.state(cell))))
+            .collect();
+        decayed.extend(
+            after
+                .iter_dying()
+                .filter(|(cell, _)| before.state(cell) < 2)
+                .map(|(cell, state)| (cell.clone(), 0, state)),
+        );
+        Self {
+            toggled,
+            deaths,
+            decayed,
+            generation_before: before.generation(),
+            generation_after: after.generation(),
+            rules: None,
+        }
+    }
+
+    /// Computes `2^step_log2` generations of the universe, see
+    /// [`Universe::step_pow2`], and returns the change.
+    ///
+    /// A single generation of [`Engine::Dense`](crate::universe::Engine::Dense) only goes through the cells
+    /// which changed, instead of comparing the whole universe.
+    pub fn step(universe: &mut Universe, step_log2: u8) -> Self {
+        let generation_before = universe.generation();
+        if step_log2 == 0 {
+            if let Some((deaths, births)) = universe.step_dense() {
+                let deaths_count = deaths.len();
+                let mut toggled = deaths;
+                toggled.extend(births);
+                return Self {
+                    toggled,
+                    deaths: deaths_count,
+                    decayed: Vec::new(),
+                    generation_before,
+                    generation_after: universe.generation(),
+                    rules: None,
+                };
+            }
+        }
+        let before = universe.snapshot();
+        universe.step_pow2(step_log2);
+        Self::between(&before, universe)
+    }
+
+    /// Number of cells which became alive.
+    pub fn births(&self) -> usize {
+        self.toggled.len() - self.deaths
+    }
+
+    /// Number of cells which died.
+    pub fn deaths(&self) -> usize {
+        self.deaths
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.toggled.is_empty()
+            && self.decayed.is_empty()
+            && self.generation_before == self.generation_after
+            && self.rules.is_none()
+    }
+
+    fn undo(&self, universe: &mut Universe) {
+        // The rule comes first, as it tells which states the cells may have
+        if let Some((rule_before, _)) = &self.rules {
+            universe.set_rule(rule_before.clone());
+        }
+        self.toggle_cells(universe);
+        for (cell, state_before, _) in &self.decayed {
+            universe.set_dying(cell.clone(), *state_before);
+        }
+        universe.set_generation(self.generation_before);
+    }
+
+    fn redo(&self, universe: &mut Universe) {
+        if let Some((_, rule_after)) = &self.rules {
+            universe.set_rule(rule_after.clone());
+        }
+        self.toggle_cells(universe);
+        for (cell, _, state_after) in &self.decayed {
+            universe.set_dying(cell.clone(), *state_after);
+        }
+        universe.set_generation(self.generation_after);
+    }
+
+    fn toggle_cells(&self, universe: &mut Universe) {
+        for cell in &self.toggled {
+            universe.toggle(cell.clone());
+        }
+    }
+}
+
+/// Dying state, 0 standing for both dead and alive cells.
+fn dying_state(state: u8) -> u8 {
+    if state < 2 {
+        0
+    } else {
+        state
+    }
+}
+
+/// Bounded stack of changes, the oldest ones being forgotten first.
+pub struct UndoHistory {
+    undo: VecDeque<Change>,
+    redo: Vec<Change>,
+    capacity: usize,
+    /// Universe when the current group of changes started.
+    group: Option<Snapshot>,
+}
+
+impl UndoHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            undo: VecDeque::with_capacity(capacity),
+            redo: Vec::new(),
+            capacity,
+            group: None,
+        }
+    }
+
+    /// Applies a modification to the universe and records it, including a
+    /// change of its rule.
+    pub fn record(&mut self, universe: &mut Universe, modification: impl FnOnce(&mut Universe)) {
+        let before = universe.snapshot();
+        let rule_before = universe.rule().clone();
+        modification(universe);
+        let mut change = Change::between(&before, universe);
+        if *universe.rule() != rule_before {
+            change.rules = Some((rule_before, universe.rule().clone()));
+        }
+        self.push(change);
+    }
+
+    /// Records a change which was already applied. Empty changes are ignored,
+    /// as well as the changes made during a group.
+    pub fn push(&mut self, change: Change) {
+        if change.is_empty() || self.capacity == 0 || self.group.is_some() {
+            return;
+        }
+        if self.undo.len() == self.capacity {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(change);
+        self.redo.clear();
+    }
+
+    /// Starts merging the following changes into a single one, recorded by
+    /// [`Self::end_group`]. No effect if a group was already started.
+    pub fn start_group(&mut self, universe: &Universe) {
+        if self.group.is_none() {
+            self.group = Some(universe.snapshot());
+        }
+    }
+
+    /// Records all the changes since [`Self::start_group`] as a single one.
+    pub fn end_group(&mut self, universe: &Universe) {
+        if let Some(before) = self.group.take() {
+            self.push(Change::between(&before, universe));
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() && self.group.is_none()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty() && self.group.is_none()
+    }
+
+    /// Reverts the last change, returns false if there was none or if a group
+    /// is started.
+    pub fn undo(&mut self, universe: &mut Universe) -> bool {
+        if self.group.is_some() {
+            return false;
+        }
+        let Some(change) = self.undo.pop_back() else {
+            return false;
+        };
+        change.undo(universe);
+        self.redo.push(change);
+        true
+    }
+
+    /// Applies again the last undone change, returns false if there was none
+    /// or if a group is started.
+    pub fn redo(&mut self, universe: &mut Universe) -> bool {
+        if self.group.is_some() {
+            return false;
+        }
+        let Some(change) = self.redo.pop() else {
+            return false;
+        };
+        change.redo(universe);
+        self.undo.push_back(change);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.group = None;
+    }
+}
+
+/// Past generations of a universe, to go back and forth in time.
+///
+/// Snapshots are sorted by generation. The oldest ones are forgotten once the
+/// total number of recorded cells, alive or dying, exceeds the configured
+/// maximum, the most recent snapshot being always kept.
+pub struct Timeline {
+    snapshots: VecDeque<Snapshot>,
+    max_cells: usize,
+    stored_cells: usize,
+}
+
+impl Timeline {
+    pub fn new(max_cells: usize) -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            max_cells,
+            stored_cells: 0,
+        }
+    }
+
+    pub fn max_cells(&self) -> usize {
+        self.max_cells
+    }
+
+    pub fn set_max_cells(&mut self, max_cells: usize) {
+        self.max_cells = max_cells;
+        self.evict();
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Snapshot> {
+        self.snapshots.get(index)
+    }
+
+    /// Index of the snapshot of the given generation.
+    pub fn position(&self, generation: u64) -> Option<usize> {
+        self.snapshots
+            .binary_search_by_key(&generation, Snapshot::generation)
+            .ok()
+    }
+
+    /// Records the current state of the universe.
+    ///
+    /// Snapshots of the same or later generations are forgotten, unless the
+    /// state is identical to the one already recorded for this generation.
+    pub fn record(&mut self, universe: &Universe) {
+        let generation = universe.generation();
+        if let Some(index) = self.position(generation) {
+            if universe.matches(&self.snapshots[index]) {
+                return;
+            }
+        }
+        let keep = self
+            .snapshots
+            .partition_point(|snapshot| snapshot.generation() < generation);
+        for snapshot in self.snapshots.drain(keep..) {
+            self.stored_cells -= snapshot.cell_count();
+        }
+        let snapshot = universe.snapshot();
+        self.stored_cells += snapshot.cell_count();
+        self.snapshots.push_back(snapshot);
+        self.evict();
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.stored_cells = 0;
+    }
+
+    fn evict(&mut self) {
+        while self.stored_cells > self.max_cells && self.snapshots.len() > 1 {
+            let snapshot = self.snapshots.pop_front().unwrap();
+            self.stored_cells -= snapshot.cell_count();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{topology::Topology, universe::Engine};
+
+    fn cell(x: isize, y: isize) -> CellPosition {
+        CellPosition { x, y }
+    }
+
+    fn toggle(history: &mut UndoHistory, universe: &mut Universe, x: isize) {
+        history.record(universe, |universe| universe.toggle(cell(x, 0)));
+    }
+
+    #[test]
+    fn undoes_and_redoes() {
+        let mut universe = Universe::new(Rule::conway(), Topology::Infinite);
+        let mut history = UndoHistory::new(10);
+        assert!(!history.can_undo());
+        toggle(&mut history, &mut universe, 0);
+        toggle(&mut history, &mut universe, 1);
+        history.record(&mut universe, |_| {});
+        assert_eq!(universe.population(), 2);
+        assert!(history.undo(&mut universe));
+        assert!(!universe.get(&cell(1, 0)) && universe.get(&cell(0, 0)));
+        assert!(history.undo(&mut universe));
+        assert_eq!(universe.population(), 0);
+        // Empty changes are not recorded
+        assert!(!history.undo(&mut universe));
+        assert!(history.redo(&mut universe));
+        assert!(universe.get(&cell(0, 0)));
+        // A new change forgets the undone ones
+        toggle(&mut history, &mut universe, 2);
+        assert!(!history.can_redo());
+        assert!(history.undo(&mut universe));
+        assert!(history.undo(&mut universe));
+        assert_eq!(universe.population(), 0);
+    }
+
+    #[test]
+    fn forgets_oldest_changes() {
+        let mut universe = Universe::new(Rule::conway(), Topology::Infinite);
+        let mut history = UndoHistory::new(3);
+        for x in 0..5 {
+            toggle(&mut history, &mut universe, x);
+        }
+        while history.undo(&mut universe) {}
+        let mut alive: Vec<CellPosition> = universe.iter_alive().cloned().collect();
+        alive.sort();
+        assert_eq!(alive, [cell(0, 0), cell(1, 0)]);
+    }
+
+    #[test]
+    fn undoes_rule_changes() {
+        let mut universe = Universe::new(Rule::conway(), Topology::Infinite);
+        let mut history = UndoHistory::new(10);
+        universe.set(cell(0, 0), true);
+        let generations: Rule = "B2/S/C3".parse().unwrap();
+        history.record(&mut universe, |universe| {
+            universe.set_rule(generations.clone());
+            universe.step();
+            universe.set(cell(5, 5), true);
+        });
+        assert_eq!(universe.state(&cell(0, 0)), 2);
+        assert!(history.undo(&mut universe));
+        assert_eq!(universe.rule(), &Rule::conway());
+        assert_eq!(universe.state(&cell(0, 0)), 1);
+        assert_eq!(universe.population(), 1);
+        assert!(history.redo(&mut universe));
+        assert_eq!(universe.rule(), &generations);
+        assert_eq!(universe.state(&cell(0, 0)), 2);
+        assert!(universe.get(&cell(5, 5)));
+        // Changing only the rule is a change too
+        history.record(&mut universe, |universe| universe.set_rule(Rule::conway()));
+        assert!(history.undo(&mut universe));
+        assert_eq!(universe.rule(), &generations);
+    }
+
+    #[test]
+    fn groups_changes() {
+        let mut universe = Universe::new(Rule::conway(), Topology::Infinite);
+        let mut history = UndoHistory::new(10);
+        toggle(&mut history, &mut universe, 0);
+        history.start_group(&universe);
+        toggle(&mut history, &mut universe, 1);
+        toggle(&mut history, &mut universe, 2);
+        assert!(!history.can_undo());
+        assert!(!history.undo(&mut universe));
+        history.end_group(&universe);
+        assert!(history.undo(&mut universe));
+        assert_eq!(universe.population(), 1);
+        assert!(history.redo(&mut universe));
+        assert_eq!(universe.population(), 3);
+    }
+
+    #[test]
+    fn timeline_forgets_oldest_generations() {
+        let mut universe = Universe::new(Rule::conway(), Topology::Infinite);
+        // Blinker, 3 cells at every generation
+        for x in -1..=1 {
+            universe.set(cell(x, 0), true);
+        }
+        let mut timeline = Timeline::new(10);
+        for _ in 0..5 {
+            timeline.record(&universe);
+            universe.step();
+        }
+        assert_eq!(timeline.len(), 3);
+        assert_eq!(timeline.get(0).unwrap().generation(), 2);
+        assert_eq!(timeline.position(4), Some(2));
+        assert_eq!(timeline.position(1), None);
+        timeline.set_max_cells(0);
+        assert_eq!(timeline.len(), 1);
+        assert_eq!(timeline.get(0).unwrap().generation(), 4);
+    }
+
+    #[test]
+    fn timeline_counts_dying_cells() {
+        // Nothing is born nor survives, a cell fades in two generations
+        let mut universe = Universe::new("B/S/C4".parse().unwrap(), Topology::Infinite);
+        universe.set(cell(0, 0), true);
+        let mut timeline = Timeline::new(2);
+        for _ in 0..3 {
+            timeline.record(&universe);
+            universe.step();
+        }
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(timeline.get(0).unwrap().generation(), 1);
+    }
+
+    #[test]
+    fn timeline_forgets_later_generations() {
+        let mut universe = Universe::new(Rule::conway(), Topology::Infinite);
+        universe.set(cell(0, 0), true);
+        let mut timeline = Timeline::new(100);
+        timeline.record(&universe);
+        universe.step();
+        timeline.record(&universe);
+        universe.step();
+        timeline.record(&universe);
+        // Recording the same state again keeps the later generations
+        universe.restore(timeline.get(1).unwrap());
+        timeline.record(&universe);
+        assert_eq!(timeline.len(), 3);
+        // Editing a past generation forgets them
+        universe.toggle(cell(5, 5));
+        timeline.record(&universe);
+        assert_eq!(timeline.len(), 2);
+        assert!(universe.matches(timeline.get(1).unwrap()));
+    }
+
+    #[test]
+    fn dense_step_records_changes() {
+        let topology = Topology::Torus {
+            width: 70,
+            height: 20,
+        };
+        // Glider and blinker
+        let cells = [
+            (0, 1),
+            (1, 0),
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (20, 5),
+            (21, 5),
+            (22, 5),
+        ];
+        let mut sparse = Universe::new(Rule::conway(), topology);
+        let mut dense = Universe::new(Rule::conway(), topology);
+        dense.set_engine(Engine::Dense);
+        for (x, y) in cells {
+            sparse.set(cell(x, y), true);
+            dense.set(cell(x, y), true);
+        }
+        let mut history = UndoHistory::new(10);
+        for _ in 0..5 {
+            let expected = Change::step(&mut sparse, 0);
+            let change = Change::step(&mut dense, 0);
+            assert_eq!(change.births(), expected.births());
+            assert_eq!(change.deaths(), expected.deaths());
+            assert!(dense.matches(&sparse.snapshot()));
+            history.push(change);
+        }
+        while history.undo(&mut dense) {}
+        assert_eq!(dense.generation(), 0);
+        assert_eq!(dense.population(), 8);
+        dense.step();
+        assert_eq!(dense.population(), 8);
+    }
+}
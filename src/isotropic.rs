@@ -0,0 +1,281 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Isotropic non-totalistic rules, written in Hensel's notation.
+//!
+//! The alive neighbours of a cell are described by a mask, one bit per
+//! neighbour in the order of the square [`Tiling`] neighbours. Configurations
+//! which only differ by a rotation or a reflection are equivalent and share the
+//! same letter, e.g. `2a` for two adjacent neighbours or `2i` for two opposite
+//! edges.
+
+use crate::{tiling::Tiling, universe::CellPosition};
+
+/// Letters of the configurations of 0 to 4 alive neighbours, the ones of 5 to
+/// 8 neighbours being the ones of their complement.
+const LETTERS: [&str; 5] = ["", "ce", "ceaikn", "ceaiknjqry", "ceaiknjqrytwz"];
+
+/// Configuration of each of the [`LETTERS`], as in Golly: 3×3 blocks read
+/// row by row, the cell itself being bit 4. No letter is needed for 0
+/// neighbours, which have a single configuration.
+const BLOCKS: [&[u16]; 5] = [
+    &[0],
+    &[1, 2],
+    &[5, 10, 3, 40, 33, 68],
+    &[69, 42, 11, 7, 98, 13, 14, 70, 41, 97],
+    &[325, 170, 15, 45, 99, 71, 106, 102, 43, 101, 105, 78, 108],
+];
+
+/// Neighbour configurations enabled for a number of alive neighbours.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Configurations {
+    All,
+    Only(Vec<char>),
+    Except(Vec<char>),
+}
+
+impl Configurations {
+    /// Whether only some of the configurations are enabled.
+    pub(crate) fn is_partial(&self) -> bool {
+        match self {
+            Self::All => false,
+            Self::Only(_) => true,
+            Self::Except(letters) => !letters.is_empty(),
+        }
+    }
+
+    fn contains(&self, letter: Option<char>) -> bool {
+        match self {
+            Self::All => true,
+            Self::Only(letters) => letter.is_some_and(|letter| letters.contains(&letter)),
+            Self::Except(letters) => !letter.is_some_and(|letter| letters.contains(&letter)),
+        }
+    }
+}
+
+/// Letters which may follow a number of alive neighbours.
+pub(crate) fn letters(count: usize) -> &'static str {
+    LETTERS[count.min(8usize.saturating_sub(count))]
+}
+
+/// Whether each neighbour mask is enabled, from the configurations enabled
+/// for each number of alive neighbours.
+pub(crate) fn transitions(counts: &[Option<Configurations>]) -> Vec<bool> {
+    (0..=u8::MAX)
+        .map(|mask| {
+            counts
+                .get(mask.count_ones() as usize)
+                .and_then(Option::as_ref)
+                .is_some_and(|configurations| configurations.contains(letter(mask)))
+        })
+        .collect()
+}
+
+/// Enabled neighbour counts, if the transitions only depend on them.
+pub(crate) fn totalistic(transitions: &[bool]) -> Option<Vec<bool>> {
+    (0..=8)
+        .map(|count| {
+            let mut enabled = (0..=u8::MAX)
+                .filter(|mask| mask.count_ones() == count)
+                .map(|mask| transitions[mask as usize]);
+            let first = enabled.next().unwrap();
+            enabled.all(|other| other == first).then_some(first)
+        })
+        .collect()
+}
+
+/// Hensel notation of the transitions, e.g. `2-a3`.
+///
+/// Letters are listed after a count when fewer than the excluded ones, which
+/// are listed after a `-` otherwise.
+pub(crate) fn notation(transitions: &[bool]) -> String {
+    let mut notation = String::new();
+    for count in 0..=8 {
+        let (enabled, disabled): (Vec<char>, Vec<char>) = letters(count)
+            .chars()
+            .partition(|letter| transitions[configuration(count, *letter) as usize]);
+        let all_enabled = match letters(count) {
+            "" => transitions[configuration(count, ' ') as usize],
+            _ => disabled.is_empty(),
+        };
+        if all_enabled {
+            notation.extend(char::from_digit(count as u32, 10));
+        } else if !enabled.is_empty() {
+            notation.extend(char::from_digit(count as u32, 10));
+            if enabled.len() <= disabled.len() {
+                notation.extend(enabled);
+            } else {
+                notation.push('-');
+                notation.extend(disabled);
+            }
+        }
+    }
+    notation
+}
+
+/// Letter of a configuration of neighbours, none when they are all dead or alive.
+fn letter(mask: u8) -> Option<char> {
+    let count = mask.count_ones() as usize;
+    let (mask, count) = if count > 4 {
+        (!mask, 8 - count)
+    } else {
+        (mask, count)
+    };
+    let symmetric: Vec<u8> = (0..8).map(|symmetry| transform(mask, symmetry)).collect();
+    LETTERS[count]
+        .chars()
+        .zip(BLOCKS[count])
+        .find(|(_, block)| symmetric.contains(&from_block(**block)))
+        .map(|(letter, _)| letter)
+}
+
+/// Configuration of `count` alive neighbours standing for a letter, which is
+/// ignored for 0 and 8 neighbours.
+fn configuration(count: usize, letter: char) -> u8 {
+    let index = LETTERS[count.min(8 - count)].find(letter).unwrap_or(0);
+    let mask = from_block(BLOCKS[count.min(8 - count)][index]);
+    if count > 4 {
+        !mask
+    } else {
+        mask
+    }
+}
+
+fn from_block(block: u16) -> u8 {
+    let mut mask = 0;
+    for bit in (0..9isize).filter(|bit| block & 1 << bit != 0) {
+        mask |= neighbour_bit((bit % 3 - 1, bit / 3 - 1));
+    }
+    mask
+}
+
+/// Applies one of the 8 rotations and reflections of the square to a mask.
+fn transform(mask: u8, symmetry: usize) -> u8 {
+    let mut result = 0;
+    for (index, (dx, dy)) in neighbours().iter().enumerate() {
+        if mask & 1 << index == 0 {
+            continue;
+        }
+        let (dx, dy) = if symmetry >= 4 {
+            (*dy, *dx)
+        } else {
+            (*dx, *dy)
+        };
+        let rotated = match symmetry % 4 {
+            0 => (dx, dy),
+            1 => (-dy, dx),
+            2 => (-dx, -dy),
+            _ => (dy, -dx),
+        };
+        result |= neighbour_bit(rotated);
+    }
+    result
+}
+
+fn neighbour_bit(offset: (isize, isize)) -> u8 {
+    neighbours()
+        .iter()
+        .position(|neighbour| *neighbour == offset)
+        .map_or(0, |index| 1 << index)
+}
+
+fn neighbours() -> &'static [(isize, isize)] {
+    Tiling::Square.neighbours(&CellPosition { x: 0, y: 0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{
+        rule::{Rule, RuleParseError},
+        universe::Universe,
+    };
+
+    #[test]
+    fn letters_cover_every_configuration() {
+        for mask in 0..=u8::MAX {
+            let count = mask.count_ones() as usize;
+            match letter(mask) {
+                None => assert!(count == 0 || count == 8),
+                Some(letter) => assert!(letters(count).contains(letter)),
+            }
+            for symmetry in 0..8 {
+                assert_eq!(letter(transform(mask, symmetry)), letter(mask));
+            }
+        }
+        for count in 1..8 {
+            for letter_char in letters(count).chars() {
+                let mask = configuration(count, letter_char);
+                assert_eq!(mask.count_ones() as usize, count);
+                assert_eq!(letter(mask), Some(letter_char));
+            }
+        }
+    }
+
+    #[test]
+    fn parses_hensel_notation() {
+        for notation in [
+            "B2-a/S12",
+            "B2ci3ai4c8/S02ea3eikjq4iz5ar6i7e",
+            "B3-k/S2-i34q",
+        ] {
+            let rule: Rule = notation.parse().unwrap();
+            assert!(rule.is_non_totalistic());
+            assert_eq!(rule.to_string(), notation);
+        }
+        // All the letters of a count are the same as the count alone
+        assert_eq!(
+            "B3ceaiknjqry/S2ceaikn3".parse::<Rule>().unwrap(),
+            Rule::conway()
+        );
+        assert_eq!(
+            "B2z/S23".parse::<Rule>(),
+            Err(RuleParseError::InvalidLetter(2, 'z'))
+        );
+        assert_eq!(
+            "B2a/S23H".parse::<Rule>(),
+            Err(RuleParseError::UnsupportedLetters)
+        );
+    }
+
+    #[test]
+    fn births_depend_on_the_configuration() {
+        let next = |rule: &str| {
+            let mut universe = Universe::new(rule.parse().unwrap(), Default::default());
+            universe.set(CellPosition { x: 0, y: 0 }, true);
+            universe.set(CellPosition { x: 1, y: 0 }, true);
+            universe.step();
+            universe
+                .iter_alive()
+                .map(|cell| (cell.x, cell.y))
+                .collect::<HashSet<_>>()
+        };
+        let adjacent = HashSet::from([(0, 1), (1, 1), (0, -1), (1, -1)]);
+        assert_eq!(next("B2a/S"), adjacent);
+        assert_eq!(next("B2/S").difference(&next("B2-a/S")).count(), 4);
+        assert!(next("B2-a/S").is_disjoint(&adjacent));
+    }
+}
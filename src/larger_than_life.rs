@@ -0,0 +1,313 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Generations of rules whose neighbourhood is larger than the 8 surrounding
+//! cells, see <https://conwaylife.com/wiki/Larger_than_Life>.
+//!
+//! The plane is split in square tiles. The cells around each tile are loaded
+//! into a summed-area table, so that counting the neighbours of a cell costs
+//! one lookup per neighbourhood row, or a single one for Moore neighbourhoods,
+//! whatever the range.
+
+use std::{
+    collections::{HashMap, HashSet},
+    thread,
+};
+
+use crate::{
+    parallel,
+    rule::{Neighbourhood, Rule},
+    topology::Topology,
+    universe::CellPosition,
+};
+
+const MIN_TILE_SIZE: isize = 64;
+
+type Tile = (isize, isize);
+
+/// Alive cells of the next generation, and the alive cells which died.
+pub(crate) fn next_generation(
+    cells: &HashSet<CellPosition>,
+    dying: &HashMap<CellPosition, u8>,
+    rule: &Rule,
+    topology: Topology,
+) -> (HashSet<CellPosition>, Vec<CellPosition>) {
+    let tile_size = MIN_TILE_SIZE.max(2 * rule.range() as isize);
+    let tile_of =
+        |cell: &CellPosition| (cell.x.div_euclid(tile_size), cell.y.div_euclid(tile_size));
+    let tiles: Vec<Tile> = match topology.bounds() {
+        Some((min, max)) => {
+            let ((x_min, y_min), (x_max, y_max)) = (tile_of(&min), tile_of(&max));
+            (y_min..=y_max)
+                .flat_map(|y| (x_min..=x_max).map(move |x| (x, y)))
+                .collect()
+        }
+        // Births are at most one tile away from an alive cell, as the range
+        // is smaller than the tile size
+        None => cells
+            .iter()
+            .map(tile_of)
+            .flat_map(|(x, y)| {
+                (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+            })
+            .collect::<HashSet<Tile>>()
+            .into_iter()
+            .collect(),
+    };
+    let next_tiles = |tiles: &[Tile]| {
+        let mut counter = NeighbourCounter::new(rule, tile_size);
+        let (mut next, mut deaths) = (Vec::new(), Vec::new());
+        for tile in tiles {
+            counter.next_tile(*tile, cells, dying, topology, &mut next, &mut deaths);
+        }
+        (next, deaths)
+    };
+    let threads = parallel::thread_count(cells.len());
+    let results = if threads == 1 {
+        vec![next_tiles(&tiles)]
+    } else {
+        thread::scope(|scope| {
+            let handles: Vec<_> = tiles
+                .chunks(tiles.len().div_ceil(threads).max(1))
+                .map(|chunk| scope.spawn(move || next_tiles(chunk)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    };
+    let mut next = HashSet::new();
+    let mut deaths = Vec::new();
+    for (tile_next, tile_deaths) in results {
+        next.extend(tile_next);
+        deaths.extend(tile_deaths);
+    }
+    (next, deaths)
+}
+
+/// Summed-area table of a tile surrounded by a margin of `range` cells.
+struct NeighbourCounter<'a> {
+    rule: &'a Rule,
+    range: isize,
+    tile_size: isize,
+    /// Width of the table, one more than the side of the loaded area.
+    stride: usize,
+    sums: Vec<u32>,
+    /// Half width of each row of the neighbourhood, from `-range` to `range`.
+    half_widths: Vec<isize>,
+}
+
+impl<'a> NeighbourCounter<'a> {
+    fn new(rule: &'a Rule, tile_size: isize) -> Self {
+        let range = rule.range() as isize;
+        let stride = (tile_size + 2 * range + 1) as usize;
+        let half_widths = (-range..=range)
+            .map(|dy| {
+                rule.neighbourhood()
+                    .half_width(rule.range(), dy.unsigned_abs()) as isize
+            })
+            .collect();
+        Self {
+            rule,
+            range,
+            tile_size,
+            stride,
+            sums: vec![0; stride * stride],
+            half_widths,
+        }
+    }
+
+    fn next_tile(
+        &mut self,
+        (tile_x, tile_y): Tile,
+        cells: &HashSet<CellPosition>,
+        dying: &HashMap<CellPosition, u8>,
+        topology: Topology,
+        next: &mut Vec<CellPosition>,
+        deaths: &mut Vec<CellPosition>,
+    ) {
+        let (x_min, y_min) = (tile_x * self.tile_size, tile_y * self.tile_size);
+        let (origin_x, origin_y) = (x_min - self.range, y_min - self.range);
+        // sums[y * stride + x] is the number of alive cells with a row below y
+        // and a column below x, relative to the origin
+        let side = self.stride - 1;
+        for row in 0..side {
+            let mut row_sum = 0;
+            for column in 0..side {
+                let pos = CellPosition {
+                    x: origin_x + column as isize,
+                    y: origin_y + row as isize,
+                };
+                if topology
+                    .wrap(&pos)
+                    .is_some_and(|cell| cells.contains(&cell))
+                {
+                    row_sum += 1;
+                }
+                let below = self.sums[row * self.stride + column + 1];
+                self.sums[(row + 1) * self.stride + column + 1] = below + row_sum;
+            }
+        }
+        for y in y_min..y_min + self.tile_size {
+            for x in x_min..x_min + self.tile_size {
+                let cell = CellPosition { x, y };
+                if !topology.contains(&cell) {
+                    continue;
+                }
+                let alive = cells.contains(&cell);
+                let mut count = self.count((x - origin_x, y - origin_y)) as usize;
+                if alive && !self.rule.includes_middle() {
+                    count -= 1;
+                }
+                if alive {
+                    if self.rule.is_survival(count) {
+                        next.push(cell);
+                    } else {
+                        deaths.push(cell);
+                    }
+                } else if self.rule.is_birth(count) && !dying.contains_key(&cell) {
+                    next.push(cell);
+                }
+            }
+        }
+    }
+
+    /// Alive cells in the neighbourhood of a cell, itself included.
+    fn count(&self, (x, y): (isize, isize)) -> u32 {
+        if self.rule.neighbourhood() == Neighbourhood::Moore {
+            return self.area(
+                (x - self.range, y - self.range),
+                (x + self.range, y + self.range),
+            );
+        }
+        (-self.range..=self.range)
+            .zip(&self.half_widths)
+            .map(|(dy, half_width)| self.area((x - half_width, y + dy), (x + half_width, y + dy)))
+            .sum()
+    }
+
+    /// Alive cells in a rectangle, corners included.
+    fn area(&self, (x0, y0): (isize, isize), (x1, y1): (isize, isize)) -> u32 {
+        let at = |x: isize, y: isize| self.sums[y as usize * self.stride + x as usize];
+        at(x1 + 1, y1 + 1) + at(x0, y0) - at(x0, y1 + 1) - at(x1 + 1, y0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    /// Counts the neighbours of every cell one by one.
+    fn naive_next_generation(
+        cells: &HashSet<CellPosition>,
+        rule: &Rule,
+        topology: Topology,
+        (min, max): (CellPosition, CellPosition),
+    ) -> HashSet<CellPosition> {
+        let range = rule.range() as isize;
+        let mut next = HashSet::new();
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let cell = CellPosition { x, y };
+                if !topology.contains(&cell) {
+                    continue;
+                }
+                let mut count = 0;
+                for dy in -range..=range {
+                    let half_width = rule
+                        .neighbourhood()
+                        .half_width(rule.range(), dy.unsigned_abs())
+                        as isize;
+                    for dx in -half_width..=half_width {
+                        if (dx, dy) == (0, 0) && !rule.includes_middle() {
+                            continue;
+                        }
+                        let neighbour = CellPosition {
+                            x: x + dx,
+                            y: y + dy,
+                        };
+                        if topology
+                            .wrap(&neighbour)
+                            .is_some_and(|neighbour| cells.contains(&neighbour))
+                        {
+                            count += 1;
+                        }
+                    }
+                }
+                let alive = cells.contains(&cell);
+                if (alive && rule.is_survival(count)) || (!alive && rule.is_birth(count)) {
+                    next.insert(cell);
+                }
+            }
+        }
+        next
+    }
+
+    #[test]
+    fn matches_naive_counting() {
+        let mut rng = StdRng::seed_from_u64(15);
+        let cells: HashSet<CellPosition> = (0..600)
+            .map(|_| CellPosition {
+                x: rng.gen_range(-20..20),
+                y: rng.gen_range(-20..20),
+            })
+            .collect();
+        let topologies = [
+            Topology::Infinite,
+            Topology::Torus {
+                width: 50,
+                height: 30,
+            },
+            Topology::Bounded {
+                width: 30,
+                height: 50,
+            },
+        ];
+        let rules = [
+            "R3,C0,M0,S10..20,B12..15,NM",
+            "R3,C0,M1,S6..12,B7..9,NN",
+            "R4,C0,M0,S15..30,B18..24,NC",
+        ];
+        for topology in topologies {
+            let cells: HashSet<CellPosition> = cells
+                .iter()
+                .filter_map(|cell| topology.wrap(cell))
+                .collect();
+            for rule in rules {
+                let rule: Rule = rule.parse().unwrap();
+                let (next, deaths) = next_generation(&cells, &HashMap::new(), &rule, topology);
+                let area = (
+                    CellPosition { x: -30, y: -30 },
+                    CellPosition { x: 30, y: 30 },
+                );
+                assert_eq!(next, naive_next_generation(&cells, &rule, topology, area));
+                let expected_deaths: HashSet<&CellPosition> = cells.difference(&next).collect();
+                assert_eq!(deaths.iter().collect::<HashSet<_>>(), expected_deaths);
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Simulation of Life-like cellular automata, independent of any rendering.
+//!
+//! The [`universe::Universe`] type holds the alive cells and computes the
+//! next generations:
+//!
+//! ```
+//! use game_of_life::universe::{CellPosition, Universe};
+//!
+//! let mut universe = Universe::default();
+//! for x in -1..=1 {
+//!     universe.set(CellPosition { x, y: 0 }, true);
+//! }
+//! universe.step();
+//! assert!(universe.get(&CellPosition { x: 0, y: 1 }));
+//! assert_eq!(universe.population(), 3);
+//! ```
+
+pub mod dense;
+pub mod hashlife;
+pub mod history;
+mod isotropic;
+mod larger_than_life;
+pub mod library;
+mod parallel;
+pub mod pattern;
+pub mod rule;
+pub mod rule_table;
+pub mod shape;
+pub mod tiling;
+pub mod topology;
+pub mod turmite;
+pub mod universe;
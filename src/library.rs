@@ -0,0 +1,404 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Catalogue of well-known patterns of Conway's Game of Life.
+//!
+//! Patterns are embedded as RLE, see [`crate::pattern::rle`].
+
+use crate::pattern::{rle, Pattern};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    StillLife,
+    Oscillator,
+    Spaceship,
+    Gun,
+    Puffer,
+    Methuselah,
+}
+
+impl Category {
+    pub const ALL: [Self; 6] = [
+        Self::StillLife,
+        Self::Oscillator,
+        Self::Spaceship,
+        Self::Gun,
+        Self::Puffer,
+        Self::Methuselah,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::StillLife => "Still life",
+            Self::Oscillator => "Oscillator",
+            Self::Spaceship => "Spaceship",
+            Self::Gun => "Gun",
+            Self::Puffer => "Puffer",
+            Self::Methuselah => "Methuselah",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LibraryPattern {
+    pub name: &'static str,
+    pub category: Category,
+    /// Generations after which the pattern repeats, possibly moved, or emits
+    /// again. `None` for methuselahs.
+    pub period: Option<u32>,
+    pub description: &'static str,
+    rle: &'static str,
+}
+
+impl LibraryPattern {
+    /// Cells of the pattern, rows going downwards from the origin.
+    pub fn pattern(&self) -> Pattern {
+        let mut pattern = rle::read(self.rle).expect("built-in patterns are valid RLE");
+        pattern.name = Some(self.name.to_string());
+        pattern.comments.push(self.description.to_string());
+        pattern.rule = Some("B3/S23".to_string());
+        pattern
+    }
+
+    /// Whether the name, category or description contains the query,
+    /// ignoring case.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        [self.name, self.category.name(), self.description]
+            .iter()
+            .any(|text| text.to_lowercase().contains(&query))
+    }
+}
+
+pub const PATTERNS: &[LibraryPattern] = &[
+    LibraryPattern {
+        name: "Block",
+        category: Category::StillLife,
+        period: Some(1),
+        description: "Smallest and most common still life.",
+        rle: "2o$2o!",
+    },
+    LibraryPattern {
+        name: "Beehive",
+        category: Category::StillLife,
+        period: Some(1),
+        description: "Six cells, the second most common still life.",
+        rle: "b2o$o2bo$b2o!",
+    },
+    LibraryPattern {
+        name: "Loaf",
+        category: Category::StillLife,
+        period: Some(1),
+        description: "Seven cells, often left over by collisions.",
+        rle: "b2o$o2bo$bobo$2bo!",
+    },
+    LibraryPattern {
+        name: "Boat",
+        category: Category::StillLife,
+        period: Some(1),
+        description: "The only five cells still life.",
+        rle: "2o$obo$bo!",
+    },
+    LibraryPattern {
+        name: "Tub",
+        category: Category::StillLife,
+        period: Some(1),
+        description: "Four cells around an empty one.",
+        rle: "bo$obo$bo!",
+    },
+    LibraryPattern {
+        name: "Pond",
+        category: Category::StillLife,
+        period: Some(1),
+        description: "Eight cells ring.",
+        rle: "b2o$o2bo$o2bo$b2o!",
+    },
+    LibraryPattern {
+        name: "Eater 1",
+        category: Category::StillLife,
+        period: Some(1),
+        description: "Fishhook shaped, destroys the gliders hitting it and recovers.",
+        rle: "2o$obo$2bo$2b2o!",
+    },
+    LibraryPattern {
+        name: "Blinker",
+        category: Category::Oscillator,
+        period: Some(2),
+        description: "Three cells in a row, the smallest oscillator.",
+        rle: "3o!",
+    },
+    LibraryPattern {
+        name: "Toad",
+        category: Category::Oscillator,
+        period: Some(2),
+        description: "Two shifted rows of three cells.",
+        rle: "b3o$3o!",
+    },
+    LibraryPattern {
+        name: "Beacon",
+        category: Category::Oscillator,
+        period: Some(2),
+        description: "Two diagonal blocks whose inner corners blink.",
+        rle: "2o$2o$2b2o$2b2o!",
+    },
+    LibraryPattern {
+        name: "Clock",
+        category: Category::Oscillator,
+        period: Some(2),
+        description: "Six cells whose hands seem to turn.",
+        rle: "2bo$obo$bobo$bo!",
+    },
+    LibraryPattern {
+        name: "Pulsar",
+        category: Category::Oscillator,
+        period: Some(3),
+        description: "Most common oscillator of period 3, with four-fold symmetry.",
+        rle: "2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo$\
+              o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+    },
+    LibraryPattern {
+        name: "Kok's galaxy",
+        category: Category::Oscillator,
+        period: Some(8),
+        description: "Four interlocked arms turning around an empty center.",
+        rle: "6ob2o$6ob2o$7b2o$2o5b2o$2o5b2o$2o5b2o$2o$2ob6o$2ob6o!",
+    },
+    LibraryPattern {
+        name: "Pentadecathlon",
+        category: Category::Oscillator,
+        period: Some(15),
+        description: "Evolves from a row of ten cells, also able to reflect gliders.",
+        rle: "2bo4bo$2ob4ob2o$2bo4bo!",
+    },
+    LibraryPattern {
+        name: "Glider",
+        category: Category::Spaceship,
+        period: Some(4),
+        description: "Smallest spaceship, moving one cell diagonally every 4 generations.",
+        rle: "bo$2bo$3o!",
+    },
+    LibraryPattern {
+        name: "Lightweight spaceship",
+        category: Category::Spaceship,
+        period: Some(4),
+        description: "LWSS, moving orthogonally at half the speed of light.",
+        rle: "bo2bo$o$o3bo$4o!",
+    },
+    LibraryPattern {
+        name: "Middleweight spaceship",
+        category: Category::Spaceship,
+        period: Some(4),
+        description: "MWSS, one column longer than the LWSS.",
+        rle: "3bo$bo3bo$o$o4bo$5o!",
+    },
+    LibraryPattern {
+        name: "Heavyweight spaceship",
+        category: Category::Spaceship,
+        period: Some(4),
+        description: "HWSS, the largest of the three standard orthogonal spaceships.",
+        rle: "3b2o$bo4bo$o$o5bo$6o!",
+    },
+    LibraryPattern {
+        name: "Copperhead",
+        category: Category::Spaceship,
+        period: Some(10),
+        description: "Slow spaceship moving one cell every 10 generations.",
+        rle: "b2o2b2o$3b2o$3b2o$obo2bobo$o6bo2$o6bo$b2o2b2o$2b4o2$3b2o$3b2o!",
+    },
+    LibraryPattern {
+        name: "Gosper glider gun",
+        category: Category::Gun,
+        period: Some(30),
+        description: "First known gun, emitting a glider every 30 generations.",
+        rle: "24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$\
+              2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!",
+    },
+    LibraryPattern {
+        name: "Simkin glider gun",
+        category: Category::Gun,
+        period: Some(120),
+        description: "Smallest known gun, emitting a glider every 120 generations.",
+        rle: "2o5b2o$2o5b2o2$4b2o$4b2o5$22b2ob2o$21bo5bo$21bo6bo2b2o$21b3o3bo3b2o$\
+              26bo4$20b2o$20bo$21b3o$23bo!",
+    },
+    LibraryPattern {
+        name: "Puffer train",
+        category: Category::Puffer,
+        period: Some(20),
+        description: "Two lightweight spaceships escorting a B-heptomino, leaving smoke behind.",
+        rle: "3bo$4bo$o3bo$b4o4$o$b2o$2bo$2bo$bo3$3bo$4bo$o3bo$b4o!",
+    },
+    LibraryPattern {
+        name: "R-pentomino",
+        category: Category::Methuselah,
+        period: None,
+        description: "Five cells taking 1103 generations to stabilize.",
+        rle: "b2o$2o$bo!",
+    },
+    LibraryPattern {
+        name: "Diehard",
+        category: Category::Methuselah,
+        period: None,
+        description: "Seven cells vanishing after 130 generations.",
+        rle: "6bo$2o$bo3b3o!",
+    },
+    LibraryPattern {
+        name: "Acorn",
+        category: Category::Methuselah,
+        period: None,
+        description: "Seven cells taking 5206 generations to stabilize.",
+        rle: "bo$3bo$2o2b3o!",
+    },
+    LibraryPattern {
+        name: "Pi-heptomino",
+        category: Category::Methuselah,
+        period: None,
+        description: "Common seven cells shape, stabilizing after 173 generations.",
+        rle: "3o$obo$obo!",
+    },
+    LibraryPattern {
+        name: "B-heptomino",
+        category: Category::Methuselah,
+        period: None,
+        description: "Stabilizes after 148 generations, found in many puffers.",
+        rle: "ob2o$3o$bo!",
+    },
+];
+
+/// Library pattern with the given name, ignoring case.
+pub fn find(name: &str) -> Option<&'static LibraryPattern> {
+    PATTERNS
+        .iter()
+        .find(|pattern| pattern.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rule::Rule,
+        topology::Topology,
+        universe::{CellPosition, Engine, Universe},
+    };
+
+    fn universe(pattern: &LibraryPattern) -> Universe {
+        let pattern = pattern.pattern();
+        let rule = pattern.rule.as_ref().unwrap().parse::<Rule>().unwrap();
+        let mut universe = Universe::new(rule, Topology::Infinite);
+        universe.paste(&pattern, &CellPosition { x: 0, y: 0 });
+        universe
+    }
+
+    /// Sorted cells, moved so that their bounding box starts at the origin.
+    fn shape(universe: &Universe) -> Vec<CellPosition> {
+        let (min, _) = universe.bounding_box().unwrap();
+        let mut cells: Vec<CellPosition> = universe
+            .iter_alive()
+            .map(|cell| CellPosition {
+                x: cell.x - min.x,
+                y: cell.y - min.y,
+            })
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn patterns_match_their_period() {
+        for library_pattern in PATTERNS {
+            let name = library_pattern.name;
+            let mut universe = universe(library_pattern);
+            assert!(universe.population() > 0, "{name}");
+            let (gun_min, gun_max) = universe.bounding_box().unwrap();
+            let gun_cells = |universe: &Universe| {
+                let mut cells = universe.region(&gun_min, &gun_max).cells;
+                cells.sort();
+                cells
+            };
+            let Some(period) = library_pattern.period else {
+                assert_eq!(library_pattern.category, Category::Methuselah, "{name}");
+                continue;
+            };
+            // Guns and puffers only repeat once their first emissions left
+            if matches!(library_pattern.category, Category::Gun | Category::Puffer) {
+                universe.advance(4 * period as u64);
+            }
+            let start_box = universe.bounding_box().unwrap();
+            let start_shape = shape(&universe);
+            let (start_population, start_gun) = (universe.population(), gun_cells(&universe));
+            for generation in 1..=period {
+                universe.step();
+                let repeated = match library_pattern.category {
+                    Category::StillLife | Category::Oscillator => {
+                        universe.bounding_box() == Some(start_box.clone())
+                            && shape(&universe) == start_shape
+                    }
+                    Category::Spaceship => shape(&universe) == start_shape,
+                    Category::Gun => gun_cells(&universe) == start_gun,
+                    Category::Puffer | Category::Methuselah => continue,
+                };
+                assert_eq!(repeated, generation == period, "{name} at {generation}");
+            }
+            match library_pattern.category {
+                // One more glider
+                Category::Gun => assert_eq!(universe.population(), start_population + 5),
+                Category::Spaceship => {
+                    assert_ne!(universe.bounding_box().unwrap(), start_box, "{name}")
+                }
+                // The front edge moves by the same distance every period, the
+                // smoke left behind taking longer to settle
+                Category::Puffer => {
+                    let edges =
+                        |(min, max): (CellPosition, CellPosition)| [min.x, min.y, max.x, max.y];
+                    let start_edges = edges(start_box);
+                    let middle_edges = edges(universe.bounding_box().unwrap());
+                    universe.advance(period as u64);
+                    let end_edges = edges(universe.bounding_box().unwrap());
+                    let front_moves = (0..4).any(|edge| {
+                        let first_move = middle_edges[edge] - start_edges[edge];
+                        first_move != 0 && end_edges[edge] - middle_edges[edge] == first_move
+                    });
+                    assert!(front_moves, "{name}");
+                }
+                _ => (),
+            }
+        }
+    }
+
+    #[test]
+    fn methuselahs_match_their_description() {
+        let mut diehard = universe(find("Diehard").unwrap());
+        diehard.advance(129);
+        assert!(diehard.population() > 0);
+        diehard.step();
+        assert_eq!(diehard.population(), 0);
+        // Final populations including the escaping gliders
+        for (name, generations, population) in [("R-pentomino", 1103, 116), ("Acorn", 5206, 633)] {
+            let mut universe = universe(find(name).unwrap());
+            universe.set_engine(Engine::HashLife);
+            universe.advance(generations);
+            assert_eq!(universe.population(), population, "{name}");
+        }
+    }
+}
@@ -23,11 +23,15 @@ SOFTWARE.
 */
 
 mod cell_system;
+mod drawing;
 mod gui;
+mod selection;
+mod turmite_system;
 
 use bevy::prelude::*;
 use cell_system::CellSystem;
 use gui::GuiSystem;
+use turmite_system::TurmiteSystem;
 
 fn main() {
     App::new()
@@ -40,6 +44,7 @@ fn main() {
             ..Default::default()
         }))
         .add_plugins(CellSystem)
+        .add_plugins(TurmiteSystem)
         .add_plugins(GuiSystem)
         .run();
 }
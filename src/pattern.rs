@@ -0,0 +1,330 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Reading and writing of pattern files.
+//!
+//! Pattern files describe rows from top to bottom, while `CellPosition`
+//! ordinates grow upwards: row `n` of a file is mapped to `y = -n`.
+
+pub mod life;
+pub mod plaintext;
+pub mod rle;
+
+use std::{error::Error, fmt};
+
+use crate::universe::CellPosition;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pattern {
+    pub name: Option<String>,
+    pub comments: Vec<String>,
+    /// Rule as written in the file, not necessarily supported by this program.
+    pub rule: Option<String>,
+    pub cells: Vec<CellPosition>,
+    /// Cells in other states than dead and alive, only supported by the RLE format.
+    pub states: Vec<(CellPosition, u8)>,
+}
+
+impl Pattern {
+    pub fn from_cells<'a>(cells: impl IntoIterator<Item = &'a CellPosition>) -> Self {
+        Self {
+            cells: cells.into_iter().cloned().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Bottom left and top right corners of the smallest box containing all cells.
+    pub fn bounding_box(&self) -> Option<(CellPosition, CellPosition)> {
+        let mut cells = self
+            .cells
+            .iter()
+            .chain(self.states.iter().map(|(cell, _)| cell));
+        let first = cells.next()?;
+        let (mut min, mut max) = (first.clone(), first.clone());
+        for cell in cells {
+            min.x = min.x.min(cell.x);
+            min.y = min.y.min(cell.y);
+            max.x = max.x.max(cell.x);
+            max.y = max.y.max(cell.y);
+        }
+        Some((min, max))
+    }
+
+    /// Transforms the cells of a `width`×`height` box whose bottom left corner
+    /// is the origin, see [`Transformation::apply`].
+    pub fn transformed(
+        mut self,
+        transformation: Transformation,
+        width: isize,
+        height: isize,
+    ) -> Self {
+        let states = self.states.iter_mut().map(|(cell, _)| cell);
+        for cell in self.cells.iter_mut().chain(states) {
+            *cell = transformation.apply(cell, width, height);
+        }
+        self
+    }
+
+    /// Moves the pattern so that its bounding box is centered on the origin.
+    pub fn centered(mut self) -> Self {
+        if let Some((min, max)) = self.bounding_box() {
+            let (dx, dy) = ((min.x + max.x) / 2, (min.y + max.y) / 2);
+            let states = self.states.iter_mut().map(|(cell, _)| cell);
+            for cell in self.cells.iter_mut().chain(states) {
+                cell.x -= dx;
+                cell.y -= dy;
+            }
+        }
+        self
+    }
+}
+
+/// Rotation or reflection of the cells of a box, on the square grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transformation {
+    RotateClockwise,
+    Rotate180,
+    RotateCounterclockwise,
+    /// Mirrors the left and right sides.
+    FlipHorizontal,
+    /// Mirrors the top and bottom sides.
+    FlipVertical,
+}
+
+impl Transformation {
+    pub const ALL: [Self; 5] = [
+        Self::RotateCounterclockwise,
+        Self::RotateClockwise,
+        Self::Rotate180,
+        Self::FlipHorizontal,
+        Self::FlipVertical,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::RotateClockwise => "Rotate right",
+            Self::Rotate180 => "Rotate 180°",
+            Self::RotateCounterclockwise => "Rotate left",
+            Self::FlipHorizontal => "Flip horizontally",
+            Self::FlipVertical => "Flip vertically",
+        }
+    }
+
+    /// Size of a `width`×`height` box once transformed.
+    pub fn size(&self, width: isize, height: isize) -> (isize, isize) {
+        match self {
+            Self::RotateClockwise | Self::RotateCounterclockwise => (height, width),
+            Self::Rotate180 | Self::FlipHorizontal | Self::FlipVertical => (width, height),
+        }
+    }
+
+    /// Moves a cell of a `width`×`height` box whose bottom left corner is the
+    /// origin, the transformed box keeping its bottom left corner there.
+    pub fn apply(&self, cell: &CellPosition, width: isize, height: isize) -> CellPosition {
+        let (x, y) = (cell.x, cell.y);
+        let (x, y) = match self {
+            Self::RotateClockwise => (y, width - 1 - x),
+            Self::Rotate180 => (width - 1 - x, height - 1 - y),
+            Self::RotateCounterclockwise => (height - 1 - y, x),
+            Self::FlipHorizontal => (width - 1 - x, y),
+            Self::FlipVertical => (x, height - 1 - y),
+        };
+        CellPosition { x, y }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Rle,
+    Plaintext,
+    Life105,
+    Life106,
+}
+
+impl Format {
+    pub const ALL: [Self; 4] = [Self::Rle, Self::Plaintext, Self::Life105, Self::Life106];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Rle => "RLE",
+            Self::Plaintext => "Plaintext",
+            Self::Life105 => "Life 1.05",
+            Self::Life106 => "Life 1.06",
+        }
+    }
+
+    /// Format usually associated with a file extension, e.g. `rle` or `cells`.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "rle" => Some(Self::Rle),
+            "cells" | "txt" => Some(Self::Plaintext),
+            "lif" | "life" => Some(Self::Life106),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format of a pattern file from its content.
+    pub fn detect(input: &str) -> Self {
+        let input = input.trim_start();
+        if input.starts_with(life::LIFE_105_HEADER) {
+            return Self::Life105;
+        }
+        if input.starts_with(life::LIFE_106_HEADER) {
+            return Self::Life106;
+        }
+        let first_line = input.lines().next().unwrap_or_default().trim_end();
+        if first_line.starts_with('!')
+            || first_line
+                .chars()
+                .all(|character| matches!(character, '.' | 'O' | '*'))
+        {
+            return Self::Plaintext;
+        }
+        Self::Rle
+    }
+
+    pub fn read(&self, input: &str) -> Result<Pattern, PatternError> {
+        match self {
+            Self::Rle => rle::read(input),
+            Self::Plaintext => plaintext::read(input),
+            Self::Life105 => life::read_105(input),
+            Self::Life106 => life::read_106(input),
+        }
+    }
+
+    pub fn write(&self, pattern: &Pattern) -> String {
+        match self {
+            Self::Rle => rle::write(pattern),
+            Self::Plaintext => plaintext::write(pattern),
+            Self::Life105 => life::write_105(pattern),
+            Self::Life106 => life::write_106(pattern),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternError {
+    InvalidHeader(String),
+    InvalidNumber(String),
+    InvalidLine(usize),
+    UnexpectedCharacter { line: usize, character: char },
+    TooManyCells(usize),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeader(header) => write!(f, "invalid header: {header}"),
+            Self::InvalidNumber(number) => write!(f, "invalid number: {number}"),
+            Self::InvalidLine(line) => write!(f, "invalid content on line {line}"),
+            Self::UnexpectedCharacter { line, character } => {
+                write!(f, "unexpected character '{character}' on line {line}")
+            }
+            Self::TooManyCells(max) => write!(f, "pattern has more than {max} cells"),
+        }
+    }
+}
+
+impl Error for PatternError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// L shape in a 3x2 box: the bottom row and the top left cell.
+    fn l_shape() -> Pattern {
+        let cells = [(0, 0), (1, 0), (2, 0), (0, 1)].map(|(x, y)| CellPosition { x, y });
+        Pattern::from_cells(&cells)
+    }
+
+    fn sorted_cells(pattern: &Pattern) -> Vec<(isize, isize)> {
+        let mut cells: Vec<(isize, isize)> =
+            pattern.cells.iter().map(|cell| (cell.x, cell.y)).collect();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn transforms_cells() {
+        let expected = [
+            (
+                Transformation::RotateClockwise,
+                [(0, 0), (0, 1), (0, 2), (1, 2)],
+            ),
+            (Transformation::Rotate180, [(0, 1), (1, 1), (2, 0), (2, 1)]),
+            (
+                Transformation::RotateCounterclockwise,
+                [(0, 0), (1, 0), (1, 1), (1, 2)],
+            ),
+            (
+                Transformation::FlipHorizontal,
+                [(0, 0), (1, 0), (2, 0), (2, 1)],
+            ),
+            (
+                Transformation::FlipVertical,
+                [(0, 0), (0, 1), (1, 1), (2, 1)],
+            ),
+        ];
+        for (transformation, cells) in expected {
+            let (width, height) = transformation.size(3, 2);
+            let transformed = l_shape().transformed(transformation, 3, 2);
+            assert_eq!(
+                sorted_cells(&transformed),
+                cells,
+                "{}",
+                transformation.name()
+            );
+            let (min, max) = transformed.bounding_box().unwrap();
+            assert_eq!((min.x, min.y), (0, 0));
+            assert_eq!((max.x + 1, max.y + 1), (width, height));
+        }
+    }
+
+    #[test]
+    fn rotations_go_back_to_the_original() {
+        let (mut pattern, mut width, mut height) = (l_shape(), 3, 2);
+        for _ in 0..4 {
+            pattern = pattern.transformed(Transformation::RotateClockwise, width, height);
+            (width, height) = Transformation::RotateClockwise.size(width, height);
+        }
+        assert_eq!(sorted_cells(&pattern), sorted_cells(&l_shape()));
+        let back = l_shape()
+            .transformed(Transformation::RotateCounterclockwise, 3, 2)
+            .transformed(Transformation::RotateClockwise, 2, 3);
+        assert_eq!(sorted_cells(&back), sorted_cells(&l_shape()));
+        for flip in [Transformation::FlipHorizontal, Transformation::FlipVertical] {
+            let back = l_shape().transformed(flip, 3, 2).transformed(flip, 3, 2);
+            assert_eq!(sorted_cells(&back), sorted_cells(&l_shape()));
+        }
+    }
+
+    #[test]
+    fn transforms_other_states() {
+        let mut pattern = l_shape();
+        pattern.states.push((CellPosition { x: 2, y: 1 }, 2));
+        let transformed = pattern.transformed(Transformation::RotateCounterclockwise, 3, 2);
+        assert_eq!(transformed.states, [(CellPosition { x: 0, y: 2 }, 2)]);
+    }
+}
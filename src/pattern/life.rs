@@ -0,0 +1,207 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Life 1.05 and Life 1.06 formats, as described on
+//! <https://conwaylife.com/wiki/Life_1.05> and
+//! <https://conwaylife.com/wiki/Life_1.06>.
+
+use std::fmt::Write;
+
+use super::{plaintext, Pattern, PatternError};
+use crate::{rule::Rule, universe::CellPosition};
+
+pub const LIFE_105_HEADER: &str = "#Life 1.05";
+pub const LIFE_106_HEADER: &str = "#Life 1.06";
+
+pub fn read_105(input: &str) -> Result<Pattern, PatternError> {
+    let mut pattern = Pattern::default();
+    let (mut block_x, mut block_y) = (0, 0);
+    for (line_idx, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(LIFE_105_HEADER) {
+            continue;
+        }
+        if let Some(directive) = line.strip_prefix('#') {
+            let mut chars = directive.chars();
+            let kind = chars.next();
+            let content = chars.as_str().trim();
+            match kind {
+                Some('D' | 'C') => pattern.comments.push(content.to_string()),
+                Some('N') => pattern.rule = Some(Rule::conway().to_string()),
+                Some('R') => {
+                    pattern.rule = Some(match content.parse::<Rule>() {
+                        Ok(rule) => rule.to_string(),
+                        Err(_) => content.to_string(),
+                    })
+                }
+                Some('P') => {
+                    (block_x, block_y) = parse_coordinates(content, line_idx)?;
+                }
+                _ => (),
+            }
+            continue;
+        }
+        plaintext::read_row(line, line_idx, block_x, block_y, &mut pattern.cells)?;
+        block_y += 1;
+    }
+    Ok(pattern)
+}
+
+pub fn write_105(pattern: &Pattern) -> String {
+    let mut output = String::new();
+    writeln!(output, "{LIFE_105_HEADER}").unwrap();
+    if let Some(name) = &pattern.name {
+        writeln!(output, "#D {name}").unwrap();
+    }
+    for comment in &pattern.comments {
+        writeln!(output, "#D {comment}").unwrap();
+    }
+    match pattern.rule.as_deref().map(str::parse::<Rule>) {
+        Some(Ok(rule)) if rule != Rule::conway() => {
+            writeln!(output, "#R {}", rule.to_survival_birth_string()).unwrap()
+        }
+        _ => writeln!(output, "#N").unwrap(),
+    }
+    if let Some((min, max)) = pattern.bounding_box() {
+        writeln!(output, "#P {} {}", min.x, -max.y).unwrap();
+        for row in plaintext::write_rows(pattern, '.', '*') {
+            writeln!(output, "{row}").unwrap();
+        }
+    }
+    output
+}
+
+pub fn read_106(input: &str) -> Result<Pattern, PatternError> {
+    let mut pattern = Pattern::default();
+    for (line_idx, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (x, y) = parse_coordinates(line, line_idx)?;
+        pattern.cells.push(CellPosition { x, y: -y });
+    }
+    Ok(pattern)
+}
+
+pub fn write_106(pattern: &Pattern) -> String {
+    let mut output = String::new();
+    writeln!(output, "{LIFE_106_HEADER}").unwrap();
+    for cell in &pattern.cells {
+        writeln!(output, "{} {}", cell.x, -cell.y).unwrap();
+    }
+    output
+}
+
+fn parse_coordinates(content: &str, line_idx: usize) -> Result<(isize, isize), PatternError> {
+    let mut numbers = content.split_whitespace().map(|number| {
+        number
+            .parse::<isize>()
+            .map_err(|_| PatternError::InvalidNumber(number.to_string()))
+    });
+    match (numbers.next(), numbers.next(), numbers.next()) {
+        (Some(x), Some(y), None) => Ok((x?, y?)),
+        _ => Err(PatternError::InvalidLine(line_idx + 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(x: isize, y: isize) -> CellPosition {
+        CellPosition { x, y }
+    }
+
+    fn sorted(mut cells: Vec<CellPosition>) -> Vec<CellPosition> {
+        cells.sort_unstable();
+        cells
+    }
+
+    #[test]
+    fn reads_105_blocks() {
+        let input = "#Life 1.05\n#D Two blinkers\n#N\n#P -1 -1\n***\n#P 5 2\n*\n*\n*\n";
+        let pattern = read_105(input).unwrap();
+        assert_eq!(pattern.comments, ["Two blinkers"]);
+        assert_eq!(pattern.rule, Some(Rule::conway().to_string()));
+        assert_eq!(
+            pattern.cells,
+            [
+                cell(-1, 1),
+                cell(0, 1),
+                cell(1, 1),
+                cell(5, -2),
+                cell(5, -3),
+                cell(5, -4)
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips_105() {
+        let pattern = Pattern {
+            comments: vec!["Comment".to_string()],
+            rule: Some("B36/S23".parse::<Rule>().unwrap().to_string()),
+            cells: vec![cell(-3, 4), cell(-1, 4), cell(2, 1)],
+            ..Default::default()
+        };
+        let output = write_105(&pattern);
+        assert!(output.contains("#R 23/36"));
+        let read_back = read_105(&output).unwrap();
+        assert_eq!(read_back.rule, pattern.rule);
+        assert_eq!(read_back.comments, pattern.comments);
+        assert_eq!(sorted(read_back.cells), sorted(pattern.cells));
+    }
+
+    #[test]
+    fn round_trips_106() {
+        let cells = vec![cell(-5, 3), cell(0, 0), cell(7, -2)];
+        let output = write_106(&Pattern::from_cells(&cells));
+        assert_eq!(output, "#Life 1.06\n-5 -3\n0 0\n7 2\n");
+        assert_eq!(read_106(&output).unwrap().cells, cells);
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert_eq!(
+            read_106("#Life 1.06\n1 2 3\n"),
+            Err(PatternError::InvalidLine(2))
+        );
+        assert_eq!(
+            read_106("#Life 1.06\n1 two\n"),
+            Err(PatternError::InvalidNumber("two".to_string()))
+        );
+        assert_eq!(
+            read_105("#Life 1.05\n#P 0\n*\n"),
+            Err(PatternError::InvalidLine(2))
+        );
+        assert_eq!(
+            read_105("#Life 1.05\n#P 0 0\n*o\n"),
+            Err(PatternError::UnexpectedCharacter {
+                line: 3,
+                character: 'o'
+            })
+        );
+    }
+}
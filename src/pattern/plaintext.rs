@@ -0,0 +1,166 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Plaintext `.cells` format, as described on
+//! <https://conwaylife.com/wiki/Plaintext>.
+
+use std::fmt::Write;
+
+use super::{Pattern, PatternError};
+use crate::universe::CellPosition;
+
+pub fn read(input: &str) -> Result<Pattern, PatternError> {
+    let mut pattern = Pattern::default();
+    let mut y = 0;
+    for (line_idx, line) in input.lines().enumerate() {
+        if let Some(comment) = line.strip_prefix('!') {
+            match comment.strip_prefix("Name:") {
+                Some(name) => pattern.name = Some(name.trim().to_string()),
+                None => pattern.comments.push(comment.trim().to_string()),
+            }
+            continue;
+        }
+        read_row(line, line_idx, 0, y, &mut pattern.cells)?;
+        y += 1;
+    }
+    Ok(pattern)
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut output = String::new();
+    if let Some(name) = &pattern.name {
+        writeln!(output, "!Name: {name}").unwrap();
+    }
+    for comment in &pattern.comments {
+        writeln!(output, "!{comment}").unwrap();
+    }
+    for row in write_rows(pattern, '.', 'O') {
+        writeln!(output, "{row}").unwrap();
+    }
+    output
+}
+
+/// Reads a row of dead (`.`) and alive (`O` or `*`) cells, whose first cell
+/// is at the provided coordinates counted from top to bottom.
+pub(super) fn read_row(
+    line: &str,
+    line_idx: usize,
+    x: isize,
+    y: isize,
+    cells: &mut Vec<CellPosition>,
+) -> Result<(), PatternError> {
+    for (delta_x, character) in line.trim_end().chars().enumerate() {
+        match character {
+            '.' => (),
+            'O' | '*' => cells.push(CellPosition {
+                x: x + delta_x as isize,
+                y: -y,
+            }),
+            c => {
+                return Err(PatternError::UnexpectedCharacter {
+                    line: line_idx + 1,
+                    character: c,
+                })
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rows of the bounding box of the pattern, trailing dead cells being omitted.
+pub(super) fn write_rows(pattern: &Pattern, dead: char, alive: char) -> Vec<String> {
+    let Some((min, max)) = pattern.bounding_box() else {
+        return Vec::new();
+    };
+    let mut rows = vec![Vec::new(); (max.y - min.y + 1) as usize];
+    for cell in &pattern.cells {
+        let row = &mut rows[(max.y - cell.y) as usize];
+        let x = (cell.x - min.x) as usize;
+        if row.len() <= x {
+            row.resize(x + 1, dead);
+        }
+        row[x] = alive;
+    }
+    rows.into_iter()
+        .map(|row| match row.is_empty() {
+            true => dead.to_string(),
+            false => String::from_iter(row),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(x: isize, y: isize) -> CellPosition {
+        CellPosition { x, y }
+    }
+
+    #[test]
+    fn reads_cells() {
+        let pattern = read("!Name: Beacon\n!Two blocks\nOO\nO.\n...*\n..**\n").unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Beacon"));
+        assert_eq!(pattern.comments, ["Two blocks"]);
+        assert_eq!(
+            pattern.cells,
+            [
+                cell(0, 0),
+                cell(1, 0),
+                cell(0, -1),
+                cell(3, -2),
+                cell(2, -3),
+                cell(3, -3)
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        let pattern = Pattern {
+            name: Some("Test".to_string()),
+            comments: vec!["Empty rows".to_string()],
+            cells: vec![cell(2, 0), cell(0, -1), cell(4, -4)],
+            ..Default::default()
+        };
+        let output = write(&pattern);
+        assert_eq!(output, "!Name: Test\n!Empty rows\n..O\nO\n.\n.\n....O\n");
+        let mut read_back = read(&output).unwrap();
+        read_back.cells.sort_unstable();
+        let mut expected = pattern;
+        expected.cells.sort_unstable();
+        assert_eq!(read_back, expected);
+    }
+
+    #[test]
+    fn rejects_unexpected_characters() {
+        assert_eq!(
+            read("!Glider\n.O.\n..X\n"),
+            Err(PatternError::UnexpectedCharacter {
+                line: 3,
+                character: 'X'
+            })
+        );
+    }
+}
@@ -0,0 +1,375 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Run Length Encoded format, as described on
+//! <https://conwaylife.com/wiki/Run_Length_Encoded>.
+//!
+//! Patterns with more than two states use Golly's extension: `.` for dead
+//! cells, `A` to `X` for states 1 to 24, and `pA` to `yO` for the next ones.
+
+use std::fmt::Write;
+
+use super::{Pattern, PatternError};
+use crate::universe::CellPosition;
+
+const MAX_LINE_LENGTH: usize = 70;
+
+/// Maximum number of cells read, so that a huge run count does not exhaust
+/// the memory.
+const MAX_CELLS: usize = 1 << 24;
+
+pub fn read(input: &str) -> Result<Pattern, PatternError> {
+    let mut pattern = Pattern::default();
+    let (mut origin_x, mut origin_y) = (0, 0);
+    let (mut x, mut y): (isize, isize) = (0, 0);
+    let mut run_count = String::new();
+    let mut header_read = false;
+    'lines: for (line_idx, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix('#') {
+            let mut chars = comment.chars();
+            let kind = chars.next();
+            let content = chars.as_str().trim();
+            match kind {
+                Some('N') => pattern.name = Some(content.to_string()),
+                Some('C' | 'c' | 'O') => pattern.comments.push(content.to_string()),
+                Some('P' | 'R') => (origin_x, origin_y) = parse_coordinates(content)?,
+                _ => (),
+            }
+            continue;
+        }
+        if !header_read && line.starts_with('x') {
+            pattern.rule = parse_header(line)?;
+            header_read = true;
+            continue;
+        }
+        let mut chars = line.chars().peekable();
+        while let Some(character) = chars.next() {
+            match character {
+                '0'..='9' => {
+                    run_count.push(character);
+                    continue;
+                }
+                c if c.is_whitespace() => continue,
+                _ => (),
+            }
+            let count = match run_count.as_str() {
+                "" => 1,
+                number => number
+                    .parse::<isize>()
+                    .map_err(|_| PatternError::InvalidNumber(number.to_string()))?,
+            };
+            let too_large = || PatternError::InvalidNumber(count.to_string());
+            run_count.clear();
+            match character {
+                'b' | '.' => x = x.checked_add(count).ok_or_else(too_large)?,
+                '$' => {
+                    x = 0;
+                    y = y.checked_add(count).ok_or_else(too_large)?;
+                }
+                '!' => break 'lines,
+                c if c.is_ascii_alphabetic() => {
+                    let state = match (c, chars.peek()) {
+                        ('A'..='X', _) => state_of(None, c),
+                        ('p'..='y', Some(next @ 'A'..='X')) => {
+                            let next = *next;
+                            chars.next();
+                            state_of(Some(c), next)
+                        }
+                        _ => Some(1),
+                    };
+                    let state = state.ok_or(PatternError::UnexpectedCharacter {
+                        line: line_idx + 1,
+                        character: c,
+                    })?;
+                    if pattern.cells.len() + pattern.states.len() + count as usize > MAX_CELLS {
+                        return Err(PatternError::TooManyCells(MAX_CELLS));
+                    }
+                    for _ in 0..count {
+                        let cell = CellPosition {
+                            x: origin_x + x,
+                            y: -(origin_y + y),
+                        };
+                        match state {
+                            1 => pattern.cells.push(cell),
+                            _ => pattern.states.push((cell, state)),
+                        }
+                        x += 1;
+                    }
+                }
+                c => {
+                    return Err(PatternError::UnexpectedCharacter {
+                        line: line_idx + 1,
+                        character: c,
+                    })
+                }
+            }
+        }
+    }
+    Ok(pattern)
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut output = String::new();
+    if let Some(name) = &pattern.name {
+        writeln!(output, "#N {name}").unwrap();
+    }
+    for comment in &pattern.comments {
+        writeln!(output, "#C {comment}").unwrap();
+    }
+    let Some((min, max)) = pattern.bounding_box() else {
+        output.push_str("x = 0, y = 0");
+        if let Some(rule) = &pattern.rule {
+            write!(output, ", rule = {rule}").unwrap();
+        }
+        output.push_str("\n!\n");
+        return output;
+    };
+    let (width, height) = (max.x - min.x + 1, max.y - min.y + 1);
+    write!(output, "x = {width}, y = {height}").unwrap();
+    if let Some(rule) = &pattern.rule {
+        write!(output, ", rule = {rule}").unwrap();
+    }
+    output.push('\n');
+
+    let multi_state = !pattern.states.is_empty();
+    let mut rows = vec![Vec::new(); height as usize];
+    let cells = pattern.cells.iter().map(|cell| (cell, 1));
+    let states = pattern.states.iter().map(|(cell, state)| (cell, *state));
+    for (cell, state) in cells.chain(states) {
+        rows[(max.y - cell.y) as usize].push((cell.x - min.x, state));
+    }
+    let mut encoder = LineWrapper::new(&mut output);
+    let mut pending_line_ends = 0;
+    for mut row in rows {
+        if row.is_empty() {
+            pending_line_ends += 1;
+            continue;
+        }
+        if pending_line_ends > 0 {
+            encoder.push_run(pending_line_ends, "$");
+        }
+        pending_line_ends = 1;
+        row.sort_unstable();
+        row.dedup_by_key(|(x, _)| *x);
+        let mut current_x = 0;
+        let mut iter = row.into_iter().peekable();
+        while let Some((start, state)) = iter.next() {
+            let mut end = start;
+            while iter.peek() == Some(&(end + 1, state)) {
+                end = iter.next().unwrap().0;
+            }
+            if start > current_x {
+                encoder.push_run(start - current_x, if multi_state { "." } else { "b" });
+            }
+            encoder.push_run(end - start + 1, &state_tag(state, multi_state));
+            current_x = end + 1;
+        }
+    }
+    encoder.push_run(1, "!");
+    output.push('\n');
+    output
+}
+
+/// State of a multi-state cell, from its letter and optional prefix.
+fn state_of(prefix: Option<char>, letter: char) -> Option<u8> {
+    let high = prefix.map_or(0, |prefix| prefix as u32 - 'o' as u32);
+    u8::try_from(24 * high + letter as u32 - 'A' as u32 + 1).ok()
+}
+
+fn state_tag(state: u8, multi_state: bool) -> String {
+    if !multi_state {
+        return "o".to_string();
+    }
+    let (high, low) = ((state - 1) / 24, (state - 1) % 24);
+    let letter = char::from(b'A' + low);
+    match high {
+        0 => letter.to_string(),
+        _ => format!("{}{letter}", char::from(b'o' + high)),
+    }
+}
+
+fn parse_header(line: &str) -> Result<Option<String>, PatternError> {
+    let mut rule = None;
+    for item in line.split(',') {
+        let Some((key, value)) = item.split_once('=') else {
+            return Err(PatternError::InvalidHeader(line.to_string()));
+        };
+        let value = value.trim();
+        match key.trim() {
+            "x" | "y" => {
+                value
+                    .parse::<usize>()
+                    .map_err(|_| PatternError::InvalidNumber(value.to_string()))?;
+            }
+            "rule" => rule = Some(value.to_string()),
+            _ => (),
+        }
+    }
+    Ok(rule)
+}
+
+fn parse_coordinates(content: &str) -> Result<(isize, isize), PatternError> {
+    let mut numbers = content.split_whitespace().map(|number| {
+        number
+            .parse::<isize>()
+            .map_err(|_| PatternError::InvalidNumber(number.to_string()))
+    });
+    match (numbers.next(), numbers.next()) {
+        (Some(x), Some(y)) => Ok((x?, y?)),
+        _ => Err(PatternError::InvalidHeader(content.to_string())),
+    }
+}
+
+/// Appends runs to the output, never splitting a run across two lines.
+struct LineWrapper<'a> {
+    output: &'a mut String,
+    line_length: usize,
+}
+
+impl<'a> LineWrapper<'a> {
+    fn new(output: &'a mut String) -> Self {
+        Self {
+            output,
+            line_length: 0,
+        }
+    }
+
+    fn push_run(&mut self, count: isize, tag: &str) {
+        let run = match count {
+            1 => tag.to_string(),
+            _ => format!("{count}{tag}"),
+        };
+        if self.line_length + run.len() > MAX_LINE_LENGTH {
+            self.output.push('\n');
+            self.line_length = 0;
+        }
+        self.output.push_str(&run);
+        self.line_length += run.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(x: isize, y: isize) -> CellPosition {
+        CellPosition { x, y }
+    }
+
+    fn sorted(mut pattern: Pattern) -> Pattern {
+        pattern.cells.sort_unstable();
+        pattern.states.sort_unstable();
+        pattern
+    }
+
+    #[test]
+    fn reads_glider() {
+        let pattern =
+            read("#N Glider\n#C A comment\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.comments, ["A comment"]);
+        assert_eq!(pattern.rule.as_deref(), Some("B3/S23"));
+        assert_eq!(
+            sorted(pattern).cells,
+            [
+                cell(0, -2),
+                cell(1, -2),
+                cell(1, 0),
+                cell(2, -2),
+                cell(2, -1)
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        // Empty rows and a row longer than a line
+        let mut cells: Vec<_> = (0..100).map(|x| cell(2 * x, 0)).collect();
+        cells.extend([cell(0, -3), cell(5, -4)]);
+        let pattern = Pattern {
+            name: Some("Test".to_string()),
+            comments: vec!["First".to_string(), "Second".to_string()],
+            rule: Some("B36/S23".to_string()),
+            cells,
+            states: Vec::new(),
+        };
+        let output = write(&pattern);
+        assert!(output.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+        assert_eq!(sorted(read(&output).unwrap()), sorted(pattern));
+    }
+
+    #[test]
+    fn round_trips_states() {
+        let pattern = Pattern {
+            rule: Some("B2/S/C60".to_string()),
+            cells: vec![cell(0, 0), cell(3, -1)],
+            states: vec![
+                (cell(1, 0), 2),
+                (cell(2, 0), 24),
+                (cell(0, -1), 25),
+                (cell(4, -1), 59),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(sorted(read(&write(&pattern)).unwrap()), sorted(pattern));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert_eq!(
+            read("x = 3, y = three\n3o!"),
+            Err(PatternError::InvalidNumber("three".to_string()))
+        );
+        assert_eq!(
+            read("x = 3 y = 1\n3o!"),
+            Err(PatternError::InvalidNumber("3 y = 1".to_string()))
+        );
+        assert_eq!(
+            read("x = 3, y = 1\n2o?!"),
+            Err(PatternError::UnexpectedCharacter {
+                line: 2,
+                character: '?'
+            })
+        );
+        assert_eq!(
+            read("#P 1\nx = 1, y = 1\no!"),
+            Err(PatternError::InvalidHeader("1".to_string()))
+        );
+        assert_eq!(
+            read("x = 1, y = 1\n999999999999o!"),
+            Err(PatternError::TooManyCells(MAX_CELLS))
+        );
+        assert_eq!(
+            read(&format!("{}o$bo!", MAX_CELLS)),
+            Err(PatternError::TooManyCells(MAX_CELLS))
+        );
+        assert_eq!(
+            read("9223372036854775807b9223372036854775807bo!"),
+            Err(PatternError::InvalidNumber(
+                "9223372036854775807".to_string()
+            ))
+        );
+    }
+}
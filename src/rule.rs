@@ -0,0 +1,653 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use std::{error::Error, fmt, str::FromStr, sync::Arc};
+
+use crate::{
+    isotropic::{self, Configurations},
+    rule_table::RuleTable,
+    tiling::Tiling,
+};
+
+/// Largest neighbour count of the single digit notation.
+const MAX_DIGIT_COUNT: usize = 9;
+/// Largest range of Larger than Life rules, the same as Golly.
+const MAX_RANGE: usize = 500;
+
+/// Shape of the cells counted as neighbours by Larger than Life rules.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Neighbourhood {
+    /// Square around the cell.
+    #[default]
+    Moore,
+    /// Diamond around the cell, i.e. cells at a Manhattan distance within the range.
+    VonNeumann,
+    /// Cells at an Euclidean distance smaller than `range + 0.5`.
+    Circular,
+}
+
+impl Neighbourhood {
+    /// Neighbours on the row `dy` rows away from the cell span from `-half_width`
+    /// to `half_width` columns, `dy` being at most `range`.
+    pub fn half_width(&self, range: usize, dy: usize) -> usize {
+        match self {
+            Self::Moore => range,
+            Self::VonNeumann => range - dy,
+            Self::Circular => (range * (range + 1) - dy * dy).isqrt(),
+        }
+    }
+
+    /// Number of neighbours, the cell itself excluded.
+    pub fn size(&self, range: usize) -> usize {
+        let rows: usize = (1..=range)
+            .map(|dy| 2 * (2 * self.half_width(range, dy) + 1))
+            .sum();
+        rows + 2 * self.half_width(range, 0)
+    }
+
+    fn letter(&self) -> char {
+        match self {
+            Self::Moore => 'M',
+            Self::VonNeumann => 'N',
+            Self::Circular => 'C',
+        }
+    }
+}
+
+/// Outer totalistic or isotropic non-totalistic rule of a Life-like cellular
+/// automaton.
+///
+/// Parsed from the usual `B3/S23` notation, the `S/B` notation `23/3`
+/// is also accepted. Generations rules add a number of states, e.g.
+/// `B2/S/C3` or `/2/3` for Brian's Brain: cells which do not survive go
+/// through `states - 2` dying states before being dead.
+///
+/// A suffix selects another [`Tiling`], e.g. `B2/S34H` on a hexagonal grid.
+/// Triangular grids have 12 neighbours, counts of 10 and more cannot be
+/// written and never match.
+///
+/// On square grids, letters after a count restrict it to some configurations
+/// of the neighbours in Hensel's notation, e.g. `B2-a/S12`: a cell is born
+/// with 2 neighbours unless they are adjacent.
+///
+/// Larger than Life rules use Golly's notation, e.g. `R5,C0,M1,S34..58,B34..45,NM`
+/// for a range of 5 cells, 2 states, the cell itself being counted as one of
+/// its neighbours, survival and birth ranges and a Moore neighbourhood.
+///
+/// Any other automaton may be loaded from a Golly rule file with
+/// [`Rule::from_table`], such rules being only written as their name. The
+/// [built-in](RuleTable::built_in) ones are also parsed from their name, e.g.
+/// `WireWorld`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Rule {
+    birth: Vec<bool>,
+    survival: Vec<bool>,
+    states: u8,
+    range: usize,
+    neighbourhood: Neighbourhood,
+    include_middle: bool,
+    tiling: Tiling,
+    /// Whether birth and survival are indexed by the configuration of the
+    /// neighbours instead of their number.
+    non_totalistic: bool,
+    table: Option<Arc<RuleTable>>,
+}
+
+impl Rule {
+    pub fn conway() -> Self {
+        Self {
+            birth: counts_to_array(&[3]),
+            survival: counts_to_array(&[2, 3]),
+            states: 2,
+            range: 1,
+            neighbourhood: Neighbourhood::Moore,
+            include_middle: false,
+            tiling: Tiling::Square,
+            non_totalistic: false,
+            table: None,
+        }
+    }
+
+    /// Rule following the transitions of a rule table.
+    pub fn from_table(table: RuleTable) -> Self {
+        Self {
+            birth: Vec::new(),
+            survival: Vec::new(),
+            states: table.states(),
+            tiling: table.neighbourhood().tiling(),
+            table: Some(Arc::new(table)),
+            ..Self::conway()
+        }
+    }
+
+    pub fn table(&self) -> Option<&RuleTable> {
+        self.table.as_deref()
+    }
+
+    pub fn tiling(&self) -> Tiling {
+        self.tiling
+    }
+
+    /// Number of cell states, including the dead and alive ones.
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    pub fn range(&self) -> usize {
+        self.range
+    }
+
+    pub fn neighbourhood(&self) -> Neighbourhood {
+        self.neighbourhood
+    }
+
+    /// Whether the cell itself is counted in its number of neighbours.
+    pub fn includes_middle(&self) -> bool {
+        self.include_middle
+    }
+
+    /// Whether the neighbours are the 8 surrounding cells, as in Conway's Game of Life.
+    pub fn has_life_neighbourhood(&self) -> bool {
+        self.tiling == Tiling::Square && !self.is_larger_than_life() && self.table.is_none()
+    }
+
+    /// Whether the neighbourhood is anything else than the cells touching the cell.
+    pub fn is_larger_than_life(&self) -> bool {
+        self.range != 1 || self.neighbourhood != Neighbourhood::Moore || self.include_middle
+    }
+
+    /// Whether the next state depends on which neighbours are alive, not only
+    /// on their number.
+    pub fn is_non_totalistic(&self) -> bool {
+        self.non_totalistic
+    }
+
+    /// Contribution of the alive neighbour at the given index of the
+    /// [`Tiling::neighbours`] of a cell to the value checked by
+    /// [`Self::is_birth`] and [`Self::is_survival`]: 1 to count neighbours,
+    /// or its own bit to build the mask of the configuration of the
+    /// neighbours of non-totalistic rules.
+    pub fn neighbour_weight(&self, index: usize) -> usize {
+        if self.non_totalistic {
+            1 << index
+        } else {
+            1
+        }
+    }
+
+    pub fn is_birth(&self, neighbours_count: usize) -> bool {
+        self.birth.get(neighbours_count).copied().unwrap_or(false)
+    }
+
+    pub fn is_survival(&self, neighbours_count: usize) -> bool {
+        self.survival
+            .get(neighbours_count)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Rule in the older `S/B` notation, e.g. `23/3` for Conway's Game of Life.
+    ///
+    /// Larger than Life rules and rule tables have no such notation and use
+    /// the usual one.
+    pub fn to_survival_birth_string(&self) -> String {
+        if self.is_larger_than_life() || self.table.is_some() {
+            return self.to_string();
+        }
+        let mut rule = format!(
+            "{}/{}",
+            self.conditions(&self.survival),
+            self.conditions(&self.birth)
+        );
+        if self.states != 2 {
+            rule = format!("{rule}/{}", self.states);
+        }
+        rule.extend(self.tiling.suffix());
+        rule
+    }
+
+    fn conditions(&self, transitions: &[bool]) -> String {
+        if self.non_totalistic {
+            isotropic::notation(transitions)
+        } else {
+            digits(transitions)
+        }
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::conway()
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(table) = &self.table {
+            return f.write_str(table.name());
+        }
+        if self.is_larger_than_life() {
+            return write!(
+                f,
+                "R{},C{},M{},S{},B{},N{}",
+                self.range,
+                if self.states == 2 { 0 } else { self.states },
+                u8::from(self.include_middle),
+                count_range(&self.survival),
+                count_range(&self.birth),
+                self.neighbourhood.letter()
+            );
+        }
+        write!(
+            f,
+            "B{}/S{}",
+            self.conditions(&self.birth),
+            self.conditions(&self.survival)
+        )?;
+        if self.states != 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        if let Some(suffix) = self.tiling.suffix() {
+            write!(f, "{suffix}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Rule {
+    type Err = RuleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(table) = RuleTable::built_in(s) {
+            return Ok(Self::from_table(table));
+        }
+        if first_char_upper(s) == Some('R') && s.contains(',') {
+            return parse_larger_than_life(s);
+        }
+        let (s, tiling) = match s.chars().last().and_then(Tiling::from_suffix) {
+            Some(tiling) => (&s[..s.len() - 1], tiling),
+            None => (s, Tiling::Square),
+        };
+        let Some((left, right)) = s.split_once('/') else {
+            return Err(RuleParseError::MissingSeparator);
+        };
+        let (right, states) = match right.split_once('/') {
+            Some((right, states)) => (right, parse_states(states)?),
+            None => (right, 2),
+        };
+        let (left, right) = (left.trim(), right.trim());
+        let (birth, survival) = match (first_char_upper(left), first_char_upper(right)) {
+            (Some('B'), Some('S')) => (&left[1..], &right[1..]),
+            (Some('S'), Some('B')) => (&right[1..], &left[1..]),
+            (Some('B' | 'S'), _) | (_, Some('B' | 'S')) => {
+                return Err(RuleParseError::InvalidPrefix)
+            }
+            // S/B notation, e.g. 23/3
+            _ => (right, left),
+        };
+        let max_count = tiling.max_neighbours().min(MAX_DIGIT_COUNT);
+        let birth = parse_counts(birth, max_count)?;
+        let survival = parse_counts(survival, max_count)?;
+        let non_totalistic = birth
+            .iter()
+            .chain(&survival)
+            .flatten()
+            .any(Configurations::is_partial);
+        let (birth, survival, non_totalistic) = if non_totalistic {
+            if tiling != Tiling::Square {
+                return Err(RuleParseError::UnsupportedLetters);
+            }
+            let birth = isotropic::transitions(&birth);
+            let survival = isotropic::transitions(&survival);
+            // Letters may still enable all the configurations of their count
+            match (
+                isotropic::totalistic(&birth),
+                isotropic::totalistic(&survival),
+            ) {
+                (Some(birth), Some(survival)) => (birth, survival, false),
+                _ => (birth, survival, true),
+            }
+        } else {
+            let enabled = |counts: Vec<Option<Configurations>>| {
+                counts.iter().map(Option::is_some).collect::<Vec<bool>>()
+            };
+            (enabled(birth), enabled(survival), false)
+        };
+        if birth[0] {
+            return Err(RuleParseError::UnsupportedBirthOnZero);
+        }
+        Ok(Self {
+            birth,
+            survival,
+            states,
+            tiling,
+            non_totalistic,
+            ..Self::conway()
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleParseError {
+    MissingSeparator,
+    InvalidPrefix,
+    InvalidCount(char),
+    DuplicatedCount(char),
+    InvalidLetter(usize, char),
+    UnsupportedLetters,
+    UnsupportedBirthOnZero,
+    InvalidStates(String),
+    InvalidParameter(String),
+    MissingParameter(char),
+}
+
+impl fmt::Display for RuleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSeparator => write!(f, "missing '/' between birth and survival parts"),
+            Self::InvalidPrefix => write!(f, "expected one 'B' part and one 'S' part"),
+            Self::InvalidCount(c) => write!(f, "invalid neighbour count '{c}'"),
+            Self::DuplicatedCount(c) => write!(f, "neighbour count '{c}' appears twice"),
+            Self::InvalidLetter(count, letter) => {
+                write!(f, "invalid letter '{letter}' after neighbour count {count}")
+            }
+            Self::UnsupportedLetters => {
+                write!(f, "neighbour configuration letters require a square grid")
+            }
+            Self::UnsupportedBirthOnZero => write!(f, "B0 rules are not supported"),
+            Self::InvalidStates(states) => {
+                write!(f, "invalid number of states '{states}', expected 2 to 255")
+            }
+            Self::InvalidParameter(parameter) => write!(f, "invalid parameter '{parameter}'"),
+            Self::MissingParameter(parameter) => write!(f, "missing '{parameter}' parameter"),
+        }
+    }
+}
+
+impl Error for RuleParseError {}
+
+fn digits(counts: &[bool]) -> String {
+    counts
+        .iter()
+        .enumerate()
+        .filter(|(_, enabled)| **enabled)
+        .map(|(count, _)| char::from_digit(count as u32, 10).unwrap())
+        .collect()
+}
+
+fn first_char_upper(s: &str) -> Option<char> {
+    s.chars().next().map(|c| c.to_ascii_uppercase())
+}
+
+/// Enabled neighbour counts, some of them possibly restricted to the
+/// configurations given by the letters following them.
+fn parse_counts(s: &str, max_count: usize) -> Result<Vec<Option<Configurations>>, RuleParseError> {
+    let mut counts = vec![None; max_count + 1];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let count = c
+            .to_digit(10)
+            .map(|d| d as usize)
+            .filter(|d| *d <= max_count)
+            .ok_or(RuleParseError::InvalidCount(c))?;
+        if counts[count].is_some() {
+            return Err(RuleParseError::DuplicatedCount(c));
+        }
+        let excluded = chars.next_if_eq(&'-').is_some();
+        let mut letters = Vec::new();
+        while let Some(letter) = chars.next_if(char::is_ascii_lowercase) {
+            if !isotropic::letters(count).contains(letter) {
+                return Err(RuleParseError::InvalidLetter(count, letter));
+            }
+            letters.push(letter);
+        }
+        counts[count] = Some(match (excluded, letters.is_empty()) {
+            (false, true) => Configurations::All,
+            (false, false) => Configurations::Only(letters),
+            (true, _) => Configurations::Except(letters),
+        });
+    }
+    Ok(counts)
+}
+
+/// Number of states of Generations rules, e.g. `C3` or `3`.
+fn parse_states(s: &str) -> Result<u8, RuleParseError> {
+    let s = s.trim();
+    let number = match first_char_upper(s) {
+        Some('C' | 'G') => &s[1..],
+        _ => s,
+    };
+    number
+        .parse::<u8>()
+        .ok()
+        .filter(|states| *states >= 2)
+        .ok_or_else(|| RuleParseError::InvalidStates(s.to_string()))
+}
+
+/// Larger than Life rule, e.g. `R5,C0,M1,S34..58,B34..45,NM`.
+fn parse_larger_than_life(s: &str) -> Result<Rule, RuleParseError> {
+    let (mut range, mut states, mut include_middle) = (None, 2, false);
+    let (mut survival, mut birth, mut neighbourhood) = (None, None, Neighbourhood::Moore);
+    for parameter in s.split(',') {
+        let parameter = parameter.trim();
+        let invalid = || RuleParseError::InvalidParameter(parameter.to_string());
+        let value = parameter.get(1..).unwrap_or_default();
+        match first_char_upper(parameter) {
+            Some('R') => {
+                range = Some(
+                    value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|range| (1..=MAX_RANGE).contains(range))
+                        .ok_or_else(invalid)?,
+                )
+            }
+            Some('C') => {
+                states = match value.parse::<u8>() {
+                    Ok(0 | 2) => 2,
+                    Ok(states) if states > 2 => states,
+                    _ => return Err(invalid()),
+                }
+            }
+            Some('M') => {
+                include_middle = match value {
+                    "0" => false,
+                    "1" => true,
+                    _ => return Err(invalid()),
+                }
+            }
+            Some('S') => survival = Some(parse_count_range(value).ok_or_else(invalid)?),
+            Some('B') => birth = Some(parse_count_range(value).ok_or_else(invalid)?),
+            Some('N') => {
+                neighbourhood = match value.to_ascii_uppercase().as_str() {
+                    "M" => Neighbourhood::Moore,
+                    "N" => Neighbourhood::VonNeumann,
+                    "C" => Neighbourhood::Circular,
+                    _ => return Err(invalid()),
+                }
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    let range = range.ok_or(RuleParseError::MissingParameter('R'))?;
+    let survival = survival.ok_or(RuleParseError::MissingParameter('S'))?;
+    let birth = birth.ok_or(RuleParseError::MissingParameter('B'))?;
+    if birth.0 == 0 {
+        return Err(RuleParseError::UnsupportedBirthOnZero);
+    }
+    let max_count = neighbourhood.size(range) + usize::from(include_middle);
+    let to_array = |(min, max): (usize, usize), name: char| {
+        if max > max_count {
+            return Err(RuleParseError::InvalidParameter(format!(
+                "{name}{min}..{max}"
+            )));
+        }
+        let mut counts = vec![false; max_count + 1];
+        counts[min..=max].fill(true);
+        Ok(counts)
+    };
+    Ok(Rule {
+        survival: to_array(survival, 'S')?,
+        birth: to_array(birth, 'B')?,
+        states,
+        range,
+        neighbourhood,
+        include_middle,
+        tiling: Tiling::Square,
+        non_totalistic: false,
+        table: None,
+    })
+}
+
+/// Inclusive range of neighbour counts, e.g. `34..58`.
+fn parse_count_range(s: &str) -> Option<(usize, usize)> {
+    let (min, max) = s.split_once("..").unwrap_or((s, s));
+    let (min, max) = (min.trim().parse().ok()?, max.trim().parse().ok()?);
+    (min <= max).then_some((min, max))
+}
+
+fn count_range(counts: &[bool]) -> String {
+    let mut enabled = counts.iter().enumerate().filter(|(_, enabled)| **enabled);
+    match (enabled.next(), enabled.next_back()) {
+        (Some((min, _)), Some((max, _))) => format!("{min}..{max}"),
+        (Some((count, _)), None) => format!("{count}..{count}"),
+        _ => String::new(),
+    }
+}
+
+fn counts_to_array(counts: &[usize]) -> Vec<bool> {
+    let mut result = vec![false; Tiling::Square.max_neighbours() + 1];
+    for count in counts {
+        result[*count] = true;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a rule and checks that its string form is parsed as the same rule.
+    fn parse(s: &str) -> Rule {
+        let rule: Rule = s.parse().unwrap();
+        assert_eq!(rule.to_string().parse::<Rule>().unwrap(), rule);
+        rule
+    }
+
+    #[test]
+    fn parses_birth_survival() {
+        let rule = parse("B36/S23");
+        assert_eq!(rule.to_string(), "B36/S23");
+        assert!(rule.is_birth(3) && rule.is_birth(6) && !rule.is_birth(2));
+        assert!(rule.is_survival(2) && rule.is_survival(3) && !rule.is_survival(4));
+        assert_eq!(parse("s23/b3"), Rule::conway());
+        assert_eq!(parse("23/3"), Rule::conway());
+        assert_eq!(parse("B3/S").to_string(), "B3/S");
+        assert_eq!(Rule::conway().to_survival_birth_string(), "23/3");
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert_eq!(
+            "B3S23".parse::<Rule>(),
+            Err(RuleParseError::MissingSeparator)
+        );
+        assert_eq!("B3/B23".parse::<Rule>(), Err(RuleParseError::InvalidPrefix));
+        assert_eq!(
+            "B39/S23".parse::<Rule>(),
+            Err(RuleParseError::InvalidCount('9'))
+        );
+        assert_eq!(
+            "B33/S23".parse::<Rule>(),
+            Err(RuleParseError::DuplicatedCount('3'))
+        );
+        assert_eq!(
+            "B03/S23".parse::<Rule>(),
+            Err(RuleParseError::UnsupportedBirthOnZero)
+        );
+    }
+
+    #[test]
+    fn parses_generations() {
+        let rule = parse("B2/S/C3");
+        assert_eq!(rule.states(), 3);
+        assert_eq!(rule.to_string(), "B2/S/C3");
+        assert_eq!(rule.to_survival_birth_string(), "/2/3");
+        assert_eq!(parse("/2/3"), rule);
+        assert_eq!(parse("B2/S/G3"), rule);
+        assert_eq!(parse("B3/S23/C2"), Rule::conway());
+        assert_eq!(
+            "B2/S/C1".parse::<Rule>(),
+            Err(RuleParseError::InvalidStates("C1".to_string()))
+        );
+        assert_eq!(
+            "B2/S/C256".parse::<Rule>(),
+            Err(RuleParseError::InvalidStates("C256".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_larger_than_life() {
+        let rule = parse("R5,C0,M1,S34..58,B34..45,NM");
+        assert_eq!(rule.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+        assert!(rule.is_larger_than_life() && rule.includes_middle());
+        assert_eq!((rule.range(), rule.states()), (5, 2));
+        assert!(rule.is_birth(34) && rule.is_birth(45) && !rule.is_birth(46));
+        assert!(rule.is_survival(58) && !rule.is_survival(33));
+        assert_eq!(
+            parse("r2,c3,m0,s2..4,b3,nn").to_string(),
+            "R2,C3,M0,S2..4,B3..3,NN"
+        );
+        assert_eq!(parse("R1,C0,M0,S2..3,B3..3,NM"), Rule::conway());
+        assert_eq!(Neighbourhood::Moore.size(1), 8);
+        assert_eq!(Neighbourhood::VonNeumann.size(2), 12);
+        assert_eq!(Neighbourhood::Circular.size(2), 20);
+    }
+
+    #[test]
+    fn rejects_invalid_larger_than_life() {
+        let error = |s: &str| s.parse::<Rule>().unwrap_err();
+        assert_eq!(
+            error("R2,C0,M0,S2..3"),
+            RuleParseError::MissingParameter('B')
+        );
+        assert_eq!(
+            error("R0,C0,M0,S2..3,B3,NM"),
+            RuleParseError::InvalidParameter("R0".to_string())
+        );
+        assert_eq!(
+            error("R1,C0,M0,S2..3,B3..9,NM"),
+            RuleParseError::InvalidParameter("B3..9".to_string())
+        );
+        assert_eq!(
+            error("R1,C0,M0,S3..2,B3,NM"),
+            RuleParseError::InvalidParameter("S3..2".to_string())
+        );
+        assert_eq!(
+            error("R1,C0,M0,S2..3,B3,NX"),
+            RuleParseError::InvalidParameter("NX".to_string())
+        );
+    }
+}
@@ -0,0 +1,861 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Golly rule files, describing cellular automata of up to 255 states by a
+//! table of transitions (`@TABLE`) or a decision tree (`@TREE`), as described
+//! on <https://golly.sourceforge.io/Help/formats.html#rule>.
+//!
+//! Cells in a state which is neither dead nor alive are stored by the
+//! [`Universe`](crate::universe::Universe) as its dying cells.
+
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+};
+
+use crate::{tiling::Tiling, topology::Topology, universe::CellPosition};
+
+/// Neighbours, clockwise from the top one.
+static MOORE: [(isize, isize); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+static VON_NEUMANN: [(isize, isize); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+static HEXAGONAL: [(isize, isize); 6] = [(0, 1), (1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1)];
+static ONE_DIMENSIONAL: [(isize, isize); 2] = [(-1, 0), (1, 0)];
+
+/// Order in which the nodes of a tree are indexed, 0 being the cell itself
+/// and `i` its `i`-th neighbour: NW, NE, SW, SE, N, W, E, S and the cell.
+const MOORE_TREE_ORDER: [usize; 9] = [8, 2, 6, 4, 1, 7, 3, 5, 0];
+/// N, W, E, S and the cell.
+const VON_NEUMANN_TREE_ORDER: [usize; 5] = [1, 4, 2, 3, 0];
+
+/// Wireworld, whose electrons made of a head and a tail move along conductors:
+/// a conductor becomes a head when 1 or 2 of its neighbours are heads.
+const WIREWORLD: &str = "\
+@RULE WireWorld
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={a}
+var c={a}
+var d={a}
+var e={a}
+var f={a}
+var g={a}
+var h={a}
+var o={0,2,3}
+var p={o}
+var q={o}
+var r={o}
+var s={o}
+var t={o}
+var u={o}
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+3,1,o,p,q,r,s,t,u,1
+3,1,1,o,p,q,r,s,t,1
+@COLORS
+1 0 64 255
+2 230 40 40
+3 230 170 0
+";
+const WIREWORLD_STATES: [&str; 4] = ["Empty", "Electron head", "Electron tail", "Conductor"];
+
+/// States of a cell and its neighbours, in the order of the [`TableNeighbourhood`].
+type Neighbourhood = [u8; 9];
+
+/// Neighbours of the cells, as named in rule tables.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableNeighbourhood {
+    Moore,
+    VonNeumann,
+    Hexagonal,
+    OneDimensional,
+}
+
+impl TableNeighbourhood {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "moore" => Some(Self::Moore),
+            "vonneumann" => Some(Self::VonNeumann),
+            "hexagonal" => Some(Self::Hexagonal),
+            "onedimensional" => Some(Self::OneDimensional),
+            _ => None,
+        }
+    }
+
+    /// Offsets of the neighbours in the order of the transitions, clockwise
+    /// from the top one or from left to right.
+    pub fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Self::Moore => &MOORE,
+            Self::VonNeumann => &VON_NEUMANN,
+            Self::Hexagonal => &HEXAGONAL,
+            Self::OneDimensional => &ONE_DIMENSIONAL,
+        }
+    }
+
+    pub fn tiling(&self) -> Tiling {
+        match self {
+            Self::Hexagonal => Tiling::Hexagonal,
+            _ => Tiling::Square,
+        }
+    }
+
+    /// Index of the image of a neighbour by a reflection.
+    fn reflected(&self, index: usize) -> usize {
+        let count = self.offsets().len();
+        match self {
+            Self::OneDimensional => count - 1 - index,
+            _ => (count - index) % count,
+        }
+    }
+
+    /// Permutations of the neighbours by which the transitions are invariant,
+    /// as indices of the image of each neighbour. `None` stands for all of
+    /// them.
+    fn symmetries(&self, name: &str) -> Option<Option<Vec<Vec<usize>>>> {
+        let count = self.offsets().len();
+        let rotations = |order: usize| {
+            count.is_multiple_of(order).then(|| {
+                (0..order)
+                    .map(|rotation| {
+                        (0..count)
+                            .map(|index| (index + rotation * count / order) % count)
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+        let reflected = |mut permutations: Vec<Vec<usize>>| {
+            let reflections: Vec<Vec<usize>> = permutations
+                .iter()
+                .map(|permutation| {
+                    (0..count)
+                        .map(|index| permutation[self.reflected(index)])
+                        .collect()
+                })
+                .collect();
+            permutations.extend(reflections);
+            permutations
+        };
+        let name = name.to_ascii_lowercase();
+        match name.as_str() {
+            "none" => rotations(1).map(Some),
+            "reflect" | "reflect_horizontal" => Some(Some(reflected(rotations(1)?))),
+            "permute" => Some(None),
+            _ => {
+                let order = name.strip_prefix("rotate")?;
+                match order.strip_suffix("reflect") {
+                    Some(order) => Some(Some(reflected(rotations(order.parse().ok()?)?))),
+                    None => rotations(order.parse().ok()?).map(Some),
+                }
+            }
+        }
+    }
+}
+
+/// Cellular automaton loaded from a Golly rule file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleTable {
+    name: String,
+    states: u8,
+    neighbourhood: TableNeighbourhood,
+    transitions: Transitions,
+    colors: Vec<Option<[u8; 3]>>,
+    /// Names of the states, only known for built-in rules.
+    state_names: Vec<&'static str>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Transitions {
+    /// Transitions of each state, the first matching one being applied. The
+    /// state of cells matching none of them does not change.
+    Table(Vec<Vec<Transition>>),
+    /// Nodes of a decision tree, the root being the last one. Each node is
+    /// indexed by the state of a cell of the neighbourhood, in the given
+    /// order, the ones of the last level giving the next state.
+    Tree {
+        nodes: Vec<Vec<u32>>,
+        order: Vec<usize>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Transition {
+    neighbours: Vec<StateSet>,
+    output: u8,
+}
+
+/// Set of cell states.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct StateSet([u64; 4]);
+
+impl StateSet {
+    fn single(state: u8) -> Self {
+        let mut set = Self::default();
+        set.insert(state);
+        set
+    }
+
+    fn insert(&mut self, state: u8) {
+        self.0[state as usize / 64] |= 1 << (state % 64);
+    }
+
+    fn contains(&self, state: u8) -> bool {
+        self.0[state as usize / 64] & 1 << (state % 64) != 0
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        Self([0, 1, 2, 3].map(|word| self.0[word] | other.0[word]))
+    }
+
+    fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..=u8::MAX).filter(|state| self.contains(*state))
+    }
+}
+
+impl RuleTable {
+    /// Parses the content of a `.rule` file.
+    pub fn parse(input: &str) -> Result<Self, RuleTableError> {
+        let mut name = None;
+        let mut sections: HashMap<String, Vec<(usize, &str)>> = HashMap::new();
+        let mut section = None;
+        for (line_idx, line) in input.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if let Some(header) = line.strip_prefix('@') {
+                let (header, content) = header.split_once(' ').unwrap_or((header, ""));
+                if header == "RULE" {
+                    name = Some(content.trim().to_string());
+                }
+                section = Some(header.to_string());
+                sections.entry(header.to_string()).or_default();
+            } else if let (Some(section), false) = (&section, line.is_empty()) {
+                sections
+                    .get_mut(section)
+                    .unwrap()
+                    .push((line_idx + 1, line));
+            }
+        }
+        let name = name
+            .filter(|name| !name.is_empty())
+            .ok_or(RuleTableError::MissingName)?;
+        let (states, neighbourhood, transitions) =
+            match (sections.get("TABLE"), sections.get("TREE")) {
+                (Some(lines), _) => parse_table(lines)?,
+                (None, Some(lines)) => parse_tree(lines)?,
+                (None, None) => return Err(RuleTableError::MissingTransitions),
+            };
+        let colors = parse_colors(sections.get("COLORS").map_or(&[], Vec::as_slice), states)?;
+        Ok(Self {
+            name,
+            states,
+            neighbourhood,
+            transitions,
+            colors,
+            state_names: Vec::new(),
+        })
+    }
+
+    /// Rule available without loading any file, e.g. `WireWorld`.
+    pub fn built_in(name: &str) -> Option<Self> {
+        name.eq_ignore_ascii_case("WireWorld").then(|| Self {
+            state_names: WIREWORLD_STATES.to_vec(),
+            ..Self::parse(WIREWORLD).unwrap()
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of cell states, including the dead one.
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    pub fn neighbourhood(&self) -> TableNeighbourhood {
+        self.neighbourhood
+    }
+
+    pub fn state_name(&self, state: u8) -> Option<&'static str> {
+        self.state_names.get(state as usize).copied()
+    }
+
+    /// Colour of a state given by the `@COLORS` section, if any.
+    pub fn color(&self, state: u8) -> Option<[u8; 3]> {
+        self.colors.get(state as usize).copied().flatten()
+    }
+
+    /// Next state of a cell, from its state followed by the ones of its neighbours.
+    pub fn next_state(&self, states: &[u8]) -> u8 {
+        match &self.transitions {
+            Transitions::Table(transitions) => transitions[states[0] as usize]
+                .iter()
+                .find(|transition| {
+                    transition
+                        .neighbours
+                        .iter()
+                        .zip(&states[1..])
+                        .all(|(set, state)| set.contains(*state))
+                })
+                .map_or(states[0], |transition| transition.output),
+            Transitions::Tree { nodes, order } => {
+                let mut value = nodes.len() as u32 - 1;
+                for index in order {
+                    value = nodes[value as usize][states[*index] as usize];
+                }
+                value as u8
+            }
+        }
+    }
+}
+
+/// Next generation of the cells of a universe following a rule table, as the
+/// alive cells and the cells in other states.
+pub(crate) fn next_generation(
+    cells: &HashSet<CellPosition>,
+    others: &HashMap<CellPosition, u8>,
+    table: &RuleTable,
+    topology: Topology,
+) -> (HashSet<CellPosition>, HashMap<CellPosition, u8>) {
+    let offsets = table.neighbourhood.offsets();
+    let state = |pos: &CellPosition| {
+        if cells.contains(pos) {
+            1
+        } else {
+            others.get(pos).copied().unwrap_or(0)
+        }
+    };
+    let neighbour = |cell: &CellPosition, (dx, dy): (isize, isize)| {
+        topology.wrap(&CellPosition {
+            x: cell.x + dx,
+            y: cell.y + dy,
+        })
+    };
+    // Cells which are dead as well as their neighbours are assumed to stay dead
+    let mut candidates = HashSet::new();
+    for cell in cells.iter().chain(others.keys()) {
+        if topology.contains(cell) {
+            candidates.insert(cell.clone());
+            candidates.extend(offsets.iter().filter_map(|offset| neighbour(cell, *offset)));
+        }
+    }
+    let mut next_states: HashMap<Neighbourhood, u8> = HashMap::new();
+    let (mut next_cells, mut next_others) = (HashSet::new(), HashMap::new());
+    for cell in candidates {
+        let mut neighbourhood: Neighbourhood = [0; 9];
+        neighbourhood[0] = state(&cell);
+        for (index, offset) in offsets.iter().enumerate() {
+            neighbourhood[index + 1] = neighbour(&cell, *offset).map_or(0, |pos| state(&pos));
+        }
+        let next_state = *next_states
+            .entry(neighbourhood)
+            .or_insert_with(|| table.next_state(&neighbourhood[..=offsets.len()]));
+        match next_state {
+            0 => (),
+            1 => {
+                next_cells.insert(cell);
+            }
+            _ => {
+                next_others.insert(cell, next_state);
+            }
+        }
+    }
+    (next_cells, next_others)
+}
+
+fn parse_table(
+    lines: &[(usize, &str)],
+) -> Result<(u8, TableNeighbourhood, Transitions), RuleTableError> {
+    let mut states = None;
+    let mut neighbourhood = None;
+    let mut symmetries = None;
+    let mut symmetries_name = "none";
+    let mut variables: HashMap<&str, StateSet> = HashMap::new();
+    let mut transitions: Option<Vec<Vec<Transition>>> = None;
+    let mut seen = HashSet::new();
+    for (line_number, line) in lines.iter().copied() {
+        let invalid_line = || RuleTableError::InvalidLine(line_number);
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            match key.trim() {
+                "n_states" => {
+                    states = Some(parse_states(value)?);
+                }
+                "neighborhood" => {
+                    neighbourhood =
+                        Some(TableNeighbourhood::from_name(value).ok_or_else(|| {
+                            RuleTableError::UnknownNeighbourhood(value.to_string())
+                        })?);
+                }
+                "symmetries" => symmetries_name = value,
+                _ => return Err(invalid_line()),
+            }
+            continue;
+        }
+        let states = states.ok_or(RuleTableError::MissingParameter("n_states"))?;
+        let neighbourhood =
+            neighbourhood.ok_or(RuleTableError::MissingParameter("neighborhood"))?;
+        if transitions.is_none() {
+            symmetries = neighbourhood
+                .symmetries(symmetries_name)
+                .ok_or_else(|| RuleTableError::UnknownSymmetries(symmetries_name.to_string()))?;
+            transitions = Some(vec![Vec::new(); states as usize]);
+        }
+        let resolve = |value: &str, variables: &HashMap<&str, StateSet>| match value.parse::<u8>() {
+            Ok(state) if state < states => Some(StateSet::single(state)),
+            Ok(_) => None,
+            Err(_) => variables.get(value).copied(),
+        };
+        let unknown_value = |value: &str| RuleTableError::UnknownValue {
+            line: line_number,
+            value: value.to_string(),
+        };
+        if let Some(declaration) = line.strip_prefix("var ") {
+            let (name, values) = declaration.split_once('=').ok_or_else(invalid_line)?;
+            let values = values
+                .trim()
+                .strip_prefix('{')
+                .and_then(|values| values.strip_suffix('}'))
+                .ok_or_else(invalid_line)?;
+            let mut set = StateSet::default();
+            for value in values.split(',').map(str::trim) {
+                set = set.union(&resolve(value, &variables).ok_or_else(|| unknown_value(value))?);
+            }
+            variables.insert(name.trim(), set);
+            continue;
+        }
+        let values: Vec<&str> = if line.contains(',') {
+            line.split(',').map(str::trim).collect()
+        } else {
+            // Compact notation of tables of at most 10 states, e.g. 0123
+            line.char_indices()
+                .filter(|(_, character)| !character.is_whitespace())
+                .map(|(index, character)| &line[index..index + character.len_utf8()])
+                .collect()
+        };
+        if values.len() != neighbourhood.offsets().len() + 2 {
+            return Err(invalid_line());
+        }
+        let mut sets = Vec::new();
+        for value in &values {
+            sets.push(resolve(value, &variables).ok_or_else(|| unknown_value(value))?);
+        }
+        // Variables appearing several times take the same value everywhere
+        let mut bound: Vec<&str> = Vec::new();
+        for value in &values {
+            if variables.contains_key(value)
+                && !bound.contains(value)
+                && values.iter().filter(|other| *other == value).count() > 1
+            {
+                bound.push(value);
+            }
+        }
+        let output = values[values.len() - 1];
+        if variables.contains_key(output) && !bound.contains(&output) {
+            return Err(unknown_value(output));
+        }
+        for binding in bindings(&bound, &variables) {
+            let mut sets = sets.clone();
+            for (set, value) in sets.iter_mut().zip(&values) {
+                if let Some(position) = bound.iter().position(|name| name == value) {
+                    *set = StateSet::single(binding[position]);
+                }
+            }
+            let output = sets.pop().unwrap().iter().next().unwrap();
+            let centre = sets.remove(0);
+            let variants = match &symmetries {
+                Some(permutations) => permutations
+                    .iter()
+                    .map(|permutation| {
+                        let mut neighbours = sets.clone();
+                        for (index, set) in sets.iter().enumerate() {
+                            neighbours[permutation[index]] = *set;
+                        }
+                        neighbours
+                    })
+                    .collect(),
+                None => permutations(sets),
+            };
+            for neighbours in variants {
+                let transition = Transition { neighbours, output };
+                for state in centre.iter() {
+                    if seen.insert((state, transition.clone())) {
+                        transitions.as_mut().unwrap()[state as usize].push(transition.clone());
+                    }
+                }
+            }
+        }
+    }
+    let states = states.ok_or(RuleTableError::MissingParameter("n_states"))?;
+    let neighbourhood = neighbourhood.ok_or(RuleTableError::MissingParameter("neighborhood"))?;
+    let transitions = transitions.unwrap_or_else(|| vec![Vec::new(); states as usize]);
+    Ok((states, neighbourhood, Transitions::Table(transitions)))
+}
+
+/// All the combinations of values of the bound variables.
+fn bindings(bound: &[&str], variables: &HashMap<&str, StateSet>) -> Vec<Vec<u8>> {
+    let mut bindings = vec![Vec::new()];
+    for name in bound {
+        bindings = bindings
+            .into_iter()
+            .flat_map(|binding| {
+                variables[name].iter().map(move |state| {
+                    let mut binding = binding.clone();
+                    binding.push(state);
+                    binding
+                })
+            })
+            .collect();
+    }
+    bindings
+}
+
+/// Distinct permutations of the neighbours, in lexicographic order.
+fn permutations(mut sets: Vec<StateSet>) -> Vec<Vec<StateSet>> {
+    sets.sort_unstable();
+    let mut permutations = vec![sets.clone()];
+    loop {
+        let Some(pivot) = (1..sets.len())
+            .rev()
+            .find(|index| sets[index - 1] < sets[*index])
+        else {
+            return permutations;
+        };
+        let successor = (pivot..sets.len())
+            .rev()
+            .find(|index| sets[pivot - 1] < sets[*index])
+            .unwrap();
+        sets.swap(pivot - 1, successor);
+        sets[pivot..].reverse();
+        permutations.push(sets.clone());
+    }
+}
+
+fn parse_tree(
+    lines: &[(usize, &str)],
+) -> Result<(u8, TableNeighbourhood, Transitions), RuleTableError> {
+    let mut states = None;
+    let mut neighbourhood = None;
+    let mut node_count = None;
+    let mut nodes: Vec<Vec<u32>> = Vec::new();
+    let mut levels = Vec::new();
+    for (line_number, line) in lines.iter().copied() {
+        let invalid_line = || RuleTableError::InvalidLine(line_number);
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim();
+            match key.trim() {
+                "num_states" => states = Some(parse_states(value)?),
+                "num_neighbors" => {
+                    neighbourhood = Some(match value {
+                        "4" => TableNeighbourhood::VonNeumann,
+                        "8" => TableNeighbourhood::Moore,
+                        _ => return Err(RuleTableError::UnknownNeighbourhood(value.to_string())),
+                    })
+                }
+                "num_nodes" => node_count = Some(value.parse().map_err(|_| invalid_line())?),
+                _ => return Err(invalid_line()),
+            }
+            continue;
+        }
+        let states = states.ok_or(RuleTableError::MissingParameter("num_states"))?;
+        let numbers = line
+            .split_whitespace()
+            .map(str::parse::<u32>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid_line())?;
+        let Some((&level, children)) = numbers.split_first() else {
+            return Err(invalid_line());
+        };
+        let valid = children.len() == states as usize
+            && children.iter().all(|child| match level {
+                1 => *child < states as u32,
+                _ => levels.get(*child as usize) == Some(&(level - 1)),
+            });
+        if !valid {
+            return Err(invalid_line());
+        }
+        nodes.push(children.to_vec());
+        levels.push(level);
+    }
+    let states = states.ok_or(RuleTableError::MissingParameter("num_states"))?;
+    let neighbourhood = neighbourhood.ok_or(RuleTableError::MissingParameter("num_neighbors"))?;
+    let order = match neighbourhood {
+        TableNeighbourhood::VonNeumann => VON_NEUMANN_TREE_ORDER.to_vec(),
+        _ => MOORE_TREE_ORDER.to_vec(),
+    };
+    if node_count != Some(nodes.len()) || levels.last() != Some(&(order.len() as u32)) {
+        return Err(RuleTableError::InvalidTree);
+    }
+    Ok((states, neighbourhood, Transitions::Tree { nodes, order }))
+}
+
+/// Colours of the states, either given one by one as `state r g b`, or as a
+/// gradient `r1 g1 b1 r2 g2 b2` from state 1 to the last one.
+fn parse_colors(
+    lines: &[(usize, &str)],
+    states: u8,
+) -> Result<Vec<Option<[u8; 3]>>, RuleTableError> {
+    let mut colors = vec![None; states as usize];
+    for (line_number, line) in lines.iter().copied() {
+        let numbers = line
+            .split_whitespace()
+            .map(str::parse::<u8>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| RuleTableError::InvalidLine(line_number))?;
+        match numbers[..] {
+            [state, r, g, b] => {
+                if let Some(color) = colors.get_mut(state as usize) {
+                    *color = Some([r, g, b]);
+                }
+            }
+            [r1, g1, b1, r2, g2, b2] => {
+                let last = states.saturating_sub(2).max(1) as f32;
+                for (state, color) in colors.iter_mut().enumerate().skip(1) {
+                    let ratio = (state - 1) as f32 / last;
+                    let lerp =
+                        |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * ratio) as u8;
+                    *color = Some([lerp(r1, r2), lerp(g1, g2), lerp(b1, b2)]);
+                }
+            }
+            _ => return Err(RuleTableError::InvalidLine(line_number)),
+        }
+    }
+    Ok(colors)
+}
+
+fn parse_states(value: &str) -> Result<u8, RuleTableError> {
+    value
+        .parse::<u8>()
+        .ok()
+        .filter(|states| *states >= 2)
+        .ok_or_else(|| RuleTableError::InvalidStates(value.to_string()))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleTableError {
+    MissingName,
+    MissingTransitions,
+    MissingParameter(&'static str),
+    InvalidStates(String),
+    UnknownNeighbourhood(String),
+    UnknownSymmetries(String),
+    UnknownValue { line: usize, value: String },
+    InvalidLine(usize),
+    InvalidTree,
+}
+
+impl fmt::Display for RuleTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingName => write!(f, "missing @RULE name"),
+            Self::MissingTransitions => write!(f, "missing @TABLE or @TREE section"),
+            Self::MissingParameter(parameter) => write!(f, "missing '{parameter}' parameter"),
+            Self::InvalidStates(states) => {
+                write!(f, "invalid number of states '{states}', expected 2 to 255")
+            }
+            Self::UnknownNeighbourhood(name) => write!(f, "unknown neighborhood '{name}'"),
+            Self::UnknownSymmetries(name) => write!(f, "unknown symmetries '{name}'"),
+            Self::UnknownValue { line, value } => {
+                write!(f, "unknown state or variable '{value}' on line {line}")
+            }
+            Self::InvalidLine(line) => write!(f, "invalid content on line {line}"),
+            Self::InvalidTree => write!(f, "inconsistent number or levels of tree nodes"),
+        }
+    }
+}
+
+impl Error for RuleTableError {}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::{rule::Rule, universe::Universe};
+
+    const LIFE_TABLE: &str = "\
+@RULE LifeTable
+# Conway's Game of Life, the neighbours being permuted
+@TABLE
+n_states:2
+neighborhood:Moore
+symmetries:permute
+var a={0,1}
+var b={a}
+var c={a}
+var d={a}
+var e={a}
+var f={a}
+var g={a}
+var h={a}
+0,1,1,1,0,0,0,0,0,1
+1,1,1,0,0,0,0,0,0,1
+1,1,1,1,0,0,0,0,0,1
+1,a,b,c,d,e,f,g,h,0
+";
+
+    /// The cell takes the state of its north neighbour, i.e. everything moves
+    /// one cell down.
+    const SHIFT_TREE: &str = "\
+@RULE ShiftDown
+@TREE
+num_states=2
+num_neighbors=4
+num_nodes=9
+1 0 0
+1 1 1
+2 0 0
+2 1 1
+3 2 2
+3 3 3
+4 4 4
+4 5 5
+5 6 7
+@COLORS
+1 10 20 30
+";
+
+    fn table(transition: &str, symmetries: &str) -> RuleTable {
+        RuleTable::parse(&format!(
+            "@RULE Test\n@TABLE\nn_states:2\nneighborhood:Moore\nsymmetries:{symmetries}\n{transition}\n"
+        ))
+        .unwrap()
+    }
+
+    /// Next state of a dead cell whose neighbours at the given indices are alive.
+    fn born(table: &RuleTable, alive: &[usize]) -> bool {
+        let mut states = [0; 9];
+        for index in alive {
+            states[index + 1] = 1;
+        }
+        table.next_state(&states) == 1
+    }
+
+    #[test]
+    fn expands_symmetries() {
+        // Born with a single alive neighbour, the north one
+        let transition = "0,1,0,0,0,0,0,0,0,1";
+        let none = table(transition, "none");
+        assert!(born(&none, &[0]) && !born(&none, &[2]));
+        let rotate4 = table(transition, "rotate4");
+        assert!([0, 2, 4, 6].iter().all(|index| born(&rotate4, &[*index])));
+        assert!(!born(&rotate4, &[1]));
+        let rotate8 = table(transition, "rotate8");
+        assert!((0..8).all(|index| born(&rotate8, &[index])));
+        // North and north east neighbours
+        let transition = "0,1,1,0,0,0,0,0,0,1";
+        let rotate4 = table(transition, "rotate4");
+        assert!(born(&rotate4, &[2, 3]) && !born(&rotate4, &[0, 7]));
+        let reflect = table(transition, "rotate4reflect");
+        assert!(born(&reflect, &[0, 7]) && born(&reflect, &[6, 5]));
+        let permute = table(transition, "permute");
+        assert!(born(&permute, &[3, 6]) && !born(&permute, &[3]));
+    }
+
+    #[test]
+    fn table_matches_life() {
+        let rule = Rule::from_table(RuleTable::parse(LIFE_TABLE).unwrap());
+        assert_eq!(rule.to_string(), "LifeTable");
+        let mut rng = StdRng::seed_from_u64(18);
+        let mut table_universe = Universe::new(
+            rule,
+            Topology::Torus {
+                width: 32,
+                height: 32,
+            },
+        );
+        let mut life_universe = Universe::new(Rule::conway(), table_universe.topology());
+        for _ in 0..400 {
+            let cell = CellPosition {
+                x: rng.gen_range(-16..16),
+                y: rng.gen_range(-16..16),
+            };
+            table_universe.set(cell.clone(), true);
+            life_universe.set(cell, true);
+        }
+        for _ in 0..30 {
+            table_universe.step();
+            life_universe.step();
+            assert!(table_universe.matches(&life_universe.snapshot()));
+        }
+    }
+
+    #[test]
+    fn follows_tree() {
+        let table = RuleTable::parse(SHIFT_TREE).unwrap();
+        assert_eq!(table.neighbourhood(), TableNeighbourhood::VonNeumann);
+        assert_eq!(table.color(1), Some([10, 20, 30]));
+        let mut universe = Universe::new(Rule::from_table(table), Topology::Infinite);
+        universe.set(CellPosition { x: 2, y: 3 }, true);
+        universe.step();
+        let cells: Vec<_> = universe.iter_alive().cloned().collect();
+        assert_eq!(cells, [CellPosition { x: 2, y: 2 }]);
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        let parse = |input: &str| RuleTable::parse(input).unwrap_err();
+        assert_eq!(parse("@TABLE\nn_states:2\n"), RuleTableError::MissingName);
+        assert_eq!(parse("@RULE Test\n"), RuleTableError::MissingTransitions);
+        assert_eq!(
+            parse("@RULE Test\n@TABLE\nn_states:1\n"),
+            RuleTableError::InvalidStates("1".to_string())
+        );
+        assert_eq!(
+            parse("@RULE Test\n@TABLE\nn_states:2\nneighborhood:Moore\n0,1,0,1\n"),
+            RuleTableError::InvalidLine(5)
+        );
+        assert_eq!(
+            parse("@RULE Test\n@TABLE\nn_states:2\nneighborhood:Moore\nsymmetries:spin\n0,1,0,0,0,0,0,0,0,1\n"),
+            RuleTableError::UnknownSymmetries("spin".to_string())
+        );
+        assert_eq!(
+            parse("@RULE Test\n@TABLE\nn_states:2\nneighborhood:Moore\n0,x,0,0,0,0,0,0,0,1\n"),
+            RuleTableError::UnknownValue {
+                line: 5,
+                value: "x".to_string()
+            }
+        );
+        assert_eq!(
+            parse(&SHIFT_TREE.replace("num_nodes=9", "num_nodes=8")),
+            RuleTableError::InvalidTree
+        );
+        assert_eq!(
+            parse(&SHIFT_TREE.replace("5 6 7", "5 6 2")),
+            RuleTableError::InvalidLine(14)
+        );
+    }
+}
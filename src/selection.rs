@@ -0,0 +1,357 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Rectangle of cells selected with the mouse, and cells copied from it.
+//!
+//! The modifications of the selected cells are returned as [`Edit`]s, either
+//! applied at once or queued until the next generation.
+
+use crate::cell_system::Edit;
+use bevy::prelude::*;
+use game_of_life::{
+    pattern::{Pattern, Transformation},
+    universe::{CellPosition, Universe},
+};
+
+/// Cells copied from a selection, its bottom left corner being the origin.
+pub struct Clipboard {
+    pub pattern: Pattern,
+    pub width: isize,
+    pub height: isize,
+}
+
+impl Clipboard {
+    /// Cells of a pattern, moved so that its bounding box starts at the origin.
+    pub fn from_pattern(pattern: Pattern) -> Option<Self> {
+        let (min, max) = pattern.bounding_box()?;
+        let mut pattern = pattern;
+        let states = pattern.states.iter_mut().map(|(cell, _)| cell);
+        for cell in pattern.cells.iter_mut().chain(states) {
+            cell.x -= min.x;
+            cell.y -= min.y;
+        }
+        Some(Self {
+            pattern,
+            width: max.x - min.x + 1,
+            height: max.y - min.y + 1,
+        })
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Selection {
+    /// Cell where the drag selecting the area started.
+    drag_start: Option<CellPosition>,
+    /// Bottom left and top right corners of the selected cells.
+    area: Option<(CellPosition, CellPosition)>,
+    clipboard: Option<Clipboard>,
+    /// Whether the clipboard follows the cursor, waiting to be placed.
+    pasting: bool,
+}
+
+impl Selection {
+    pub fn area(&self) -> Option<&(CellPosition, CellPosition)> {
+        self.area.as_ref()
+    }
+
+    pub fn clipboard(&self) -> Option<&Clipboard> {
+        self.clipboard.as_ref()
+    }
+
+    pub fn is_pasting(&self) -> bool {
+        self.pasting
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag_start.is_some()
+    }
+
+    pub fn start_drag(&mut self, cell: CellPosition) {
+        self.area = Some((cell.clone(), cell.clone()));
+        self.drag_start = Some(cell);
+        self.pasting = false;
+    }
+
+    /// Extends the area from the cell where the drag started to the given one.
+    pub fn drag_to(&mut self, cell: &CellPosition) {
+        let Some(start) = &self.drag_start else {
+            return;
+        };
+        self.area = Some((
+            CellPosition {
+                x: start.x.min(cell.x),
+                y: start.y.min(cell.y),
+            },
+            CellPosition {
+                x: start.x.max(cell.x),
+                y: start.y.max(cell.y),
+            },
+        ));
+    }
+
+    pub fn end_drag(&mut self) {
+        self.drag_start = None;
+    }
+
+    /// Forgets the selected area, and stops pasting.
+    pub fn deselect(&mut self) {
+        self.drag_start = None;
+        self.area = None;
+        self.pasting = false;
+    }
+
+    pub fn copy(&mut self, universe: &Universe) {
+        if let Some((min, max)) = &self.area {
+            self.clipboard = Some(Clipboard {
+                pattern: universe.region(min, max),
+                width: max.x - min.x + 1,
+                height: max.y - min.y + 1,
+            });
+        }
+    }
+
+    pub fn cut(&mut self, universe: &Universe) -> Option<Edit> {
+        self.copy(universe);
+        self.delete()
+    }
+
+    pub fn delete(&self) -> Option<Edit> {
+        let (min, max) = self.area.clone()?;
+        Some(Box::new(move |universe: &mut Universe| {
+            universe.clear_region(&min, &max)
+        }))
+    }
+
+    /// Sets all the selected cells to a state.
+    pub fn fill(&self, state: u8) -> Option<Edit> {
+        let (min, max) = self.area.clone()?;
+        Some(Box::new(move |universe: &mut Universe| {
+            universe.fill_region(&min, &max, state)
+        }))
+    }
+
+    /// Rotates or flips the cells being pasted, or else the selected cells
+    /// around the center of the selection.
+    pub fn transform(&mut self, transformation: Transformation) -> Option<Edit> {
+        if self.pasting {
+            if let Some(clipboard) = self.clipboard.take() {
+                let (width, height) = transformation.size(clipboard.width, clipboard.height);
+                self.clipboard = Some(Clipboard {
+                    pattern: clipboard.pattern.transformed(
+                        transformation,
+                        clipboard.width,
+                        clipboard.height,
+                    ),
+                    width,
+                    height,
+                });
+            }
+            return None;
+        }
+        let (min, max) = self.area.clone()?;
+        let (width, height) = (max.x - min.x + 1, max.y - min.y + 1);
+        let (new_width, new_height) = transformation.size(width, height);
+        let new_min = CellPosition {
+            x: min.x + (width - new_width) / 2,
+            y: min.y + (height - new_height) / 2,
+        };
+        self.area = Some((
+            new_min.clone(),
+            CellPosition {
+                x: new_min.x + new_width - 1,
+                y: new_min.y + new_height - 1,
+            },
+        ));
+        Some(Box::new(move |universe: &mut Universe| {
+            let pattern = universe
+                .region(&min, &max)
+                .transformed(transformation, width, height);
+            universe.clear_region(&min, &max);
+            universe.paste(&pattern, &new_min);
+        }))
+    }
+
+    /// Moves the selected cells.
+    pub fn nudge(&mut self, dx: isize, dy: isize) -> Option<Edit> {
+        let (min, max) = self.area.as_mut()?;
+        let (from_min, from_max) = (min.clone(), max.clone());
+        min.x += dx;
+        min.y += dy;
+        max.x += dx;
+        max.y += dy;
+        let moved = min.clone();
+        Some(Box::new(move |universe: &mut Universe| {
+            let pattern = universe.region(&from_min, &from_max);
+            universe.clear_region(&from_min, &from_max);
+            universe.paste(&pattern, &moved);
+        }))
+    }
+
+    pub fn set_clipboard(&mut self, clipboard: Clipboard) {
+        self.clipboard = Some(clipboard);
+    }
+
+    /// Makes the clipboard follow the cursor until [`Self::paste`] is called.
+    pub fn start_pasting(&mut self) {
+        self.pasting = self.clipboard.is_some();
+    }
+
+    /// Bottom left corner of the clipboard when centered on the cursor.
+    pub fn paste_offset(&self, cursor: &CellPosition) -> Option<CellPosition> {
+        let clipboard = self.clipboard.as_ref()?;
+        Some(CellPosition {
+            x: cursor.x - (clipboard.width - 1) / 2,
+            y: cursor.y - (clipboard.height - 1) / 2,
+        })
+    }
+
+    /// Adds the clipboard centered on the cursor, the pasted cells becoming
+    /// the selected ones.
+    pub fn paste(&mut self, cursor: &CellPosition) -> Option<Edit> {
+        let offset = self.paste_offset(cursor)?;
+        let clipboard = self.clipboard.as_ref()?;
+        self.area = Some((
+            offset.clone(),
+            CellPosition {
+                x: offset.x + clipboard.width - 1,
+                y: offset.y + clipboard.height - 1,
+            },
+        ));
+        self.pasting = false;
+        let pattern = clipboard.pattern.clone();
+        Some(Box::new(move |universe: &mut Universe| {
+            universe.paste(&pattern, &offset)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(x: isize, y: isize) -> CellPosition {
+        CellPosition { x, y }
+    }
+
+    fn select(min: CellPosition, max: CellPosition) -> Selection {
+        let mut selection = Selection::default();
+        selection.start_drag(min);
+        selection.drag_to(&max);
+        selection.end_drag();
+        selection
+    }
+
+    #[test]
+    fn transforms_around_the_center() {
+        let mut universe = Universe::default();
+        // Row of 5 cells with a cell above its left end, in a 5x2 selection
+        for x in 0..5 {
+            universe.set(cell(x, 0), true);
+        }
+        universe.set(cell(0, 1), true);
+        universe.set(cell(10, 10), true);
+        let mut selection = select(cell(0, 0), cell(4, 1));
+        let edit = selection
+            .transform(Transformation::RotateClockwise)
+            .unwrap();
+        edit(&mut universe);
+        let (min, max) = selection.area().unwrap().clone();
+        assert_eq!((min.clone(), max.clone()), (cell(1, -1), cell(2, 3)));
+        let mut moved: Vec<CellPosition> = universe
+            .iter_alive()
+            .filter(|alive| **alive != cell(10, 10))
+            .cloned()
+            .collect();
+        moved.sort();
+        assert_eq!(moved.len(), 6);
+        assert!(moved
+            .iter()
+            .all(|moved| (min.x..=max.x).contains(&moved.x) && (min.y..=max.y).contains(&moved.y)));
+        assert_eq!(moved[0], cell(1, -1));
+        // Cells out of the selection are kept
+        assert!(universe.get(&cell(10, 10)));
+        // Back to the original area after a full turn
+        for _ in 0..3 {
+            selection
+                .transform(Transformation::RotateClockwise)
+                .unwrap()(&mut universe);
+        }
+        assert_eq!(selection.area(), Some(&(cell(0, 0), cell(4, 1))));
+        assert!(universe.get(&cell(0, 1)) && !universe.get(&cell(4, 1)));
+    }
+
+    #[test]
+    fn nudges_cells() {
+        let mut universe = Universe::default();
+        universe.set(cell(0, 0), true);
+        universe.set(cell(1, 1), true);
+        let mut selection = select(cell(0, 0), cell(1, 1));
+        selection.nudge(3, -1).unwrap()(&mut universe);
+        assert_eq!(selection.area(), Some(&(cell(3, -1), cell(4, 0))));
+        let mut alive: Vec<CellPosition> = universe.iter_alive().cloned().collect();
+        alive.sort();
+        assert_eq!(alive, [cell(3, -1), cell(4, 0)]);
+    }
+
+    #[test]
+    fn transforms_clipboard_while_pasting() {
+        let mut selection = select(cell(0, 0), cell(2, 0));
+        let mut universe = Universe::default();
+        universe.set(cell(0, 0), true);
+        selection.copy(&universe);
+        selection.start_pasting();
+        assert!(selection
+            .transform(Transformation::RotateCounterclockwise)
+            .is_none());
+        let clipboard = selection.clipboard().unwrap();
+        assert_eq!((clipboard.width, clipboard.height), (1, 3));
+        assert_eq!(clipboard.pattern.cells, [cell(0, 0)]);
+    }
+
+    #[test]
+    fn pastes_centered_on_the_cursor() {
+        let mut universe = Universe::default();
+        for (x, y) in [(0, 0), (2, 0), (1, 1)] {
+            universe.set(cell(x, y), true);
+        }
+        let mut selection = select(cell(0, 0), cell(2, 1));
+        assert_eq!(selection.paste_offset(&cell(10, 10)), None);
+        selection.copy(&universe);
+        // 3x2 clipboard, the extra row going above the cursor
+        assert_eq!(selection.paste_offset(&cell(10, 10)), Some(cell(9, 10)));
+        selection.start_pasting();
+        assert!(selection.is_pasting());
+        selection.paste(&cell(10, 10)).unwrap()(&mut universe);
+        assert!(!selection.is_pasting());
+        assert_eq!(selection.area(), Some(&(cell(9, 10), cell(11, 11))));
+        for (x, y) in [(9, 10), (11, 10), (10, 11)] {
+            assert!(universe.get(&cell(x, y)));
+        }
+        assert_eq!(universe.population(), 6);
+        // Square clipboard of odd size, exactly centered
+        let corners = Pattern::from_cells(&[cell(0, 0), cell(2, 2)]);
+        selection.set_clipboard(Clipboard::from_pattern(corners).unwrap());
+        assert_eq!(selection.paste_offset(&cell(-5, 5)), Some(cell(-6, 4)));
+    }
+}
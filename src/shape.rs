@@ -0,0 +1,192 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Cells covered by the lines and shapes drawn between two cells.
+//!
+//! Shapes are computed on the cell coordinates, which are only the expected
+//! ones on the square grid.
+
+use std::collections::HashSet;
+
+use crate::universe::CellPosition;
+
+/// Cells of a line from one cell to another, both included, using
+/// Bresenham's algorithm.
+pub fn line(from: &CellPosition, to: &CellPosition) -> Vec<CellPosition> {
+    let (dx, dy) = ((to.x - from.x).abs(), -(to.y - from.y).abs());
+    let (step_x, step_y) = ((to.x - from.x).signum(), (to.y - from.y).signum());
+    let (mut x, mut y, mut error) = (from.x, from.y, dx + dy);
+    let mut cells = Vec::with_capacity(dx.max(-dy) as usize + 1);
+    loop {
+        cells.push(CellPosition { x, y });
+        if x == to.x && y == to.y {
+            return cells;
+        }
+        let double_error = 2 * error;
+        if double_error >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if double_error <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+/// Cells of the rectangle whose opposite corners are the given cells.
+pub fn rectangle(
+    corner: &CellPosition,
+    opposite: &CellPosition,
+    filled: bool,
+) -> Vec<CellPosition> {
+    let (min, max) = bounds(corner, opposite);
+    (min.y..=max.y)
+        .flat_map(|y| (min.x..=max.x).map(move |x| CellPosition { x, y }))
+        .filter(|cell| {
+            filled || cell.x == min.x || cell.x == max.x || cell.y == min.y || cell.y == max.y
+        })
+        .collect()
+}
+
+/// Cells of the ellipse inscribed in the rectangle whose opposite corners
+/// are the given cells, an outline being made of the cells of the filled
+/// ellipse next to a cell outside of it.
+pub fn ellipse(corner: &CellPosition, opposite: &CellPosition, filled: bool) -> Vec<CellPosition> {
+    let (min, max) = bounds(corner, opposite);
+    let (center_x, center_y) = ((min.x + max.x) as f32 / 2.0, (min.y + max.y) as f32 / 2.0);
+    let (radius_x, radius_y) = (
+        (max.x - min.x) as f32 / 2.0 + 0.5,
+        (max.y - min.y) as f32 / 2.0 + 0.5,
+    );
+    let inside = |x: isize, y: isize| {
+        let (relative_x, relative_y) = (
+            (x as f32 - center_x) / radius_x,
+            (y as f32 - center_y) / radius_y,
+        );
+        relative_x * relative_x + relative_y * relative_y <= 1.0
+    };
+    let cells: HashSet<_> = (min.y..=max.y)
+        .flat_map(|y| (min.x..=max.x).map(move |x| (x, y)))
+        .filter(|(x, y)| inside(*x, *y))
+        .collect();
+    let mut shape: Vec<_> = cells
+        .iter()
+        .filter(|(x, y)| {
+            filled
+                || [(0, -1), (-1, 0), (1, 0), (0, 1)]
+                    .iter()
+                    .any(|(dx, dy)| !cells.contains(&(x + dx, y + dy)))
+        })
+        .map(|(x, y)| CellPosition { x: *x, y: *y })
+        .collect();
+    shape.sort();
+    shape
+}
+
+/// Bottom left and top right corners of the box with the given opposite corners.
+fn bounds(corner: &CellPosition, opposite: &CellPosition) -> (CellPosition, CellPosition) {
+    (
+        CellPosition {
+            x: corner.x.min(opposite.x),
+            y: corner.y.min(opposite.y),
+        },
+        CellPosition {
+            x: corner.x.max(opposite.x),
+            y: corner.y.max(opposite.y),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(x: isize, y: isize) -> CellPosition {
+        CellPosition { x, y }
+    }
+
+    #[test]
+    fn draws_lines_without_gaps() {
+        let from = cell(3, -2);
+        for (dx, dy) in [(7, 3), (3, 7), (5, 5), (6, 0), (0, 6)] {
+            for (sign_x, sign_y) in [(1, 1), (-1, 1), (1, -1), (-1, -1)] {
+                let to = cell(from.x + sign_x * dx, from.y + sign_y * dy);
+                let cells = line(&from, &to);
+                assert_eq!(cells.first(), Some(&from));
+                assert_eq!(cells.last(), Some(&to));
+                assert_eq!(cells.len() as isize, dx.max(dy) + 1, "to {to:?}");
+                for pair in cells.windows(2) {
+                    let step = ((pair[1].x - pair[0].x).abs(), (pair[1].y - pair[0].y).abs());
+                    assert!(step.0 <= 1 && step.1 <= 1 && step != (0, 0), "to {to:?}");
+                }
+            }
+        }
+        assert_eq!(line(&from, &from), [from]);
+    }
+
+    #[test]
+    fn draws_single_cells() {
+        let corner = cell(-4, 7);
+        for filled in [false, true] {
+            assert_eq!(rectangle(&corner, &corner, filled), [cell(-4, 7)]);
+            assert_eq!(ellipse(&corner, &corner, filled), [cell(-4, 7)]);
+        }
+    }
+
+    #[test]
+    fn draws_rectangles() {
+        // Corners in any order
+        let (corner, opposite) = (cell(4, -1), cell(0, 2));
+        assert_eq!(rectangle(&corner, &opposite, true).len(), 5 * 4);
+        let outline = rectangle(&corner, &opposite, false);
+        assert_eq!(outline.len(), 2 * 5 + 2 * 4 - 4);
+        assert!(!outline.contains(&cell(2, 0)));
+        assert!(outline.contains(&cell(0, -1)) && outline.contains(&cell(4, 2)));
+        // Thin rectangles have no inside
+        assert_eq!(rectangle(&cell(0, 0), &cell(1, 5), false).len(), 2 * 6);
+    }
+
+    #[test]
+    fn draws_ellipses() {
+        let (corner, opposite) = (cell(3, 3), cell(-3, -3));
+        let filled = ellipse(&corner, &opposite, true);
+        // 7x7 box without 3 cells in each corner
+        assert_eq!(filled.len(), 49 - 4 * 3);
+        for side in [cell(0, 3), cell(0, -3), cell(3, 0), cell(-3, 0)] {
+            assert!(filled.contains(&side));
+        }
+        let outline = ellipse(&corner, &opposite, false);
+        assert!(outline.len() < filled.len());
+        let is_filled = |x: isize, y: isize| filled.contains(&cell(x, y));
+        for inner in &filled {
+            let has_outside_neighbour = [(0, -1), (-1, 0), (1, 0), (0, 1)]
+                .iter()
+                .any(|(dx, dy)| !is_filled(inner.x + dx, inner.y + dy));
+            assert_eq!(outline.contains(inner), has_outside_neighbour, "{inner:?}");
+        }
+        // Flat ellipses fill their box
+        assert_eq!(ellipse(&cell(0, 0), &cell(4, 0), false).len(), 5);
+    }
+}
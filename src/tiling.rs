@@ -0,0 +1,326 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Shapes of the cells, and how they are laid out on the plane.
+//!
+//! Whatever the tiling, cells are addressed by [`CellPosition`] coordinates:
+//! - hexagonal cells use axial coordinates, the neighbours of a cell being
+//!   the ones of the square grid except its top right and bottom left ones,
+//!   as in Golly;
+//! - triangles alternate pointing up and down along a row, cells whose
+//!   coordinates have an even sum pointing up.
+
+use crate::universe::CellPosition;
+
+const SQRT_3: f32 = 1.732_050_8;
+/// Height of the rows of hexagons and triangles, whose centres are one unit apart.
+const ROW_HEIGHT: f32 = SQRT_3 / 2.0;
+
+static SQUARE_NEIGHBOURS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+static HEXAGONAL_NEIGHBOURS: [(isize, isize); 6] =
+    [(0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1)];
+
+static SQUARE_ADJACENT: [(isize, isize); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+
+static TRIANGLE_UP_ADJACENT: [(isize, isize); 3] = [(0, -1), (-1, 0), (1, 0)];
+
+static TRIANGLE_DOWN_ADJACENT: [(isize, isize); 3] = [(-1, 0), (1, 0), (0, 1)];
+
+/// Cells sharing an edge or a corner with a triangle pointing up.
+static TRIANGLE_UP_NEIGHBOURS: [(isize, isize); 12] = [
+    (-2, -1),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (2, -1),
+    (-2, 0),
+    (-1, 0),
+    (1, 0),
+    (2, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Cells sharing an edge or a corner with a triangle pointing down.
+static TRIANGLE_DOWN_NEIGHBOURS: [(isize, isize); 12] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-2, 0),
+    (-1, 0),
+    (1, 0),
+    (2, 0),
+    (-2, 1),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+    (2, 1),
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tiling {
+    #[default]
+    Square,
+    Hexagonal,
+    Triangular,
+}
+
+impl Tiling {
+    pub const ALL: [Self; 3] = [Self::Square, Self::Hexagonal, Self::Triangular];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Square => "Square",
+            Self::Hexagonal => "Hexagonal",
+            Self::Triangular => "Triangular",
+        }
+    }
+
+    /// Suffix of the rulestrings using this tiling, e.g. `B2/S34H`.
+    pub fn suffix(&self) -> Option<char> {
+        match self {
+            Self::Square => None,
+            Self::Hexagonal => Some('H'),
+            Self::Triangular => Some('L'),
+        }
+    }
+
+    pub fn from_suffix(suffix: char) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|tiling| tiling.suffix() == Some(suffix.to_ascii_uppercase()))
+    }
+
+    /// Offsets of the neighbours of a cell.
+    pub fn neighbours(&self, cell: &CellPosition) -> &'static [(isize, isize)] {
+        match self {
+            Self::Square => &SQUARE_NEIGHBOURS,
+            Self::Hexagonal => &HEXAGONAL_NEIGHBOURS,
+            Self::Triangular if points_up(cell) => &TRIANGLE_UP_NEIGHBOURS,
+            Self::Triangular => &TRIANGLE_DOWN_NEIGHBOURS,
+        }
+    }
+
+    /// Offsets of the cells sharing an edge with a cell.
+    pub fn adjacent(&self, cell: &CellPosition) -> &'static [(isize, isize)] {
+        match self {
+            Self::Square => &SQUARE_ADJACENT,
+            Self::Hexagonal => &HEXAGONAL_NEIGHBOURS,
+            Self::Triangular if points_up(cell) => &TRIANGLE_UP_ADJACENT,
+            Self::Triangular => &TRIANGLE_DOWN_ADJACENT,
+        }
+    }
+
+    pub fn max_neighbours(&self) -> usize {
+        match self {
+            Self::Square => SQUARE_NEIGHBOURS.len(),
+            Self::Hexagonal => HEXAGONAL_NEIGHBOURS.len(),
+            Self::Triangular => TRIANGLE_UP_NEIGHBOURS.len(),
+        }
+    }
+
+    /// Position on the plane of the centre of a cell.
+    pub fn center(&self, cell: &CellPosition) -> (f32, f32) {
+        let (x, y) = (cell.x as f32, cell.y as f32);
+        match self {
+            Self::Square => (x, y),
+            Self::Hexagonal => (x + y / 2.0, y * ROW_HEIGHT),
+            Self::Triangular if points_up(cell) => (x / 2.0, (y + 1.0 / 3.0) * ROW_HEIGHT),
+            Self::Triangular => (x / 2.0, (y + 2.0 / 3.0) * ROW_HEIGHT),
+        }
+    }
+
+    /// Corners of a cell, counterclockwise.
+    pub fn corners(&self, cell: &CellPosition) -> Vec<(f32, f32)> {
+        let (center_x, center_y) = self.center(cell);
+        match self {
+            Self::Square => vec![
+                (center_x - 0.5, center_y - 0.5),
+                (center_x + 0.5, center_y - 0.5),
+                (center_x + 0.5, center_y + 0.5),
+                (center_x - 0.5, center_y + 0.5),
+            ],
+            Self::Hexagonal => {
+                let radius = 1.0 / SQRT_3;
+                (0..6)
+                    .map(|corner| {
+                        let angle = (30.0 + 60.0 * corner as f32).to_radians();
+                        (
+                            center_x + radius * angle.cos(),
+                            center_y + radius * angle.sin(),
+                        )
+                    })
+                    .collect()
+            }
+            Self::Triangular => {
+                let (bottom, top) = (cell.y as f32 * ROW_HEIGHT, (cell.y + 1) as f32 * ROW_HEIGHT);
+                if points_up(cell) {
+                    vec![
+                        (center_x - 0.5, bottom),
+                        (center_x + 0.5, bottom),
+                        (center_x, top),
+                    ]
+                } else {
+                    vec![
+                        (center_x, bottom),
+                        (center_x + 0.5, top),
+                        (center_x - 0.5, top),
+                    ]
+                }
+            }
+        }
+    }
+
+    /// Cell containing a point of the plane.
+    pub fn cell_at(&self, x: f32, y: f32) -> CellPosition {
+        match self {
+            Self::Square => CellPosition {
+                x: x.round() as isize,
+                y: y.round() as isize,
+            },
+            Self::Hexagonal => {
+                // Rounding of cube coordinates
+                let axial_y = y / ROW_HEIGHT;
+                let axial_x = x - axial_y / 2.0;
+                let axial_z = -axial_x - axial_y;
+                let (mut cell_x, mut cell_y, cell_z) =
+                    (axial_x.round(), axial_y.round(), axial_z.round());
+                let (diff_x, diff_y, diff_z) = (
+                    (cell_x - axial_x).abs(),
+                    (cell_y - axial_y).abs(),
+                    (cell_z - axial_z).abs(),
+                );
+                if diff_x > diff_y && diff_x > diff_z {
+                    cell_x = -cell_y - cell_z;
+                } else if diff_y > diff_z {
+                    cell_y = -cell_x - cell_z;
+                }
+                CellPosition {
+                    x: cell_x as isize,
+                    y: cell_y as isize,
+                }
+            }
+            Self::Triangular => {
+                let row = (y / ROW_HEIGHT).floor() as isize;
+                let column = (2.0 * x).round() as isize;
+                (column - 1..=column + 1)
+                    .map(|column| CellPosition { x: column, y: row })
+                    .find(|cell| contains_point(&self.corners(cell), (x, y)))
+                    .unwrap_or(CellPosition { x: column, y: row })
+            }
+        }
+    }
+
+    /// Cells whose centre may be within a rectangle of the plane, as the
+    /// bottom left and top right corners of a box of cell coordinates.
+    pub fn cells_covering(
+        &self,
+        (x_min, y_min): (f32, f32),
+        (x_max, y_max): (f32, f32),
+    ) -> (CellPosition, CellPosition) {
+        let (min, max) = match self {
+            Self::Square => ((x_min, y_min), (x_max, y_max)),
+            Self::Hexagonal => {
+                let (row_min, row_max) = (y_min / ROW_HEIGHT, y_max / ROW_HEIGHT);
+                (
+                    (x_min - row_max / 2.0, row_min),
+                    (x_max - row_min / 2.0, row_max),
+                )
+            }
+            Self::Triangular => (
+                (2.0 * x_min, y_min / ROW_HEIGHT),
+                (2.0 * x_max, y_max / ROW_HEIGHT),
+            ),
+        };
+        (
+            CellPosition {
+                x: min.0.floor() as isize - 1,
+                y: min.1.floor() as isize - 1,
+            },
+            CellPosition {
+                x: max.0.ceil() as isize + 1,
+                y: max.1.ceil() as isize + 1,
+            },
+        )
+    }
+
+    /// Corners of the outline of the board whose bottom left and top right
+    /// cells are provided, counterclockwise.
+    pub fn board_corners(&self, min: &CellPosition, max: &CellPosition) -> [(f32, f32); 4] {
+        let (x_min, y_min, x_max, y_max) = (
+            min.x as f32 - 0.5,
+            min.y as f32 - 0.5,
+            max.x as f32 + 0.5,
+            max.y as f32 + 0.5,
+        );
+        match self {
+            Self::Square => [
+                (x_min, y_min),
+                (x_max, y_min),
+                (x_max, y_max),
+                (x_min, y_max),
+            ],
+            Self::Hexagonal => [
+                (x_min, y_min),
+                (x_max, y_min),
+                (x_max, y_max),
+                (x_min, y_max),
+            ]
+            .map(|(x, y)| (x + y / 2.0, y * ROW_HEIGHT)),
+            Self::Triangular => {
+                let (x_min, x_max) = (min.x as f32 / 2.0 - 0.5, max.x as f32 / 2.0 + 0.5);
+                let (y_min, y_max) = (min.y as f32 * ROW_HEIGHT, (max.y + 1) as f32 * ROW_HEIGHT);
+                [
+                    (x_min, y_min),
+                    (x_max, y_min),
+                    (x_max, y_max),
+                    (x_min, y_max),
+                ]
+            }
+        }
+    }
+}
+
+fn points_up(cell: &CellPosition) -> bool {
+    (cell.x + cell.y).rem_euclid(2) == 0
+}
+
+/// Whether a point is inside a convex polygon whose corners are counterclockwise.
+fn contains_point(corners: &[(f32, f32)], (x, y): (f32, f32)) -> bool {
+    corners
+        .iter()
+        .zip(corners.iter().cycle().skip(1))
+        .all(|((x0, y0), (x1, y1))| (x1 - x0) * (y - y0) - (y1 - y0) * (x - x0) >= 0.0)
+}
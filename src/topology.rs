@@ -0,0 +1,325 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use crate::{tiling::Tiling, universe::CellPosition};
+
+/// Shape of the world on which cells live.
+///
+/// Finite topologies are `width` × `height` boards centered on the origin,
+/// i.e. spanning `-width / 2..width - width / 2` horizontally (same for the
+/// vertical axis).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Topology {
+    #[default]
+    Infinite,
+    /// Opposite edges are joined.
+    Torus { width: usize, height: usize },
+    /// Cells outside of the board are always dead.
+    Bounded { width: usize, height: usize },
+    /// Left and right edges are joined, top and bottom edges are joined with
+    /// a horizontal flip.
+    KleinBottle { width: usize, height: usize },
+    /// Both pairs of opposite edges are joined with a flip.
+    CrossSurface { width: usize, height: usize },
+}
+
+impl Topology {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Infinite => "Infinite plane",
+            Self::Torus { .. } => "Torus",
+            Self::Bounded { .. } => "Bounded box",
+            Self::KleinBottle { .. } => "Klein bottle",
+            Self::CrossSurface { .. } => "Cross-surface",
+        }
+    }
+
+    /// All topologies, finite ones having the provided size.
+    pub fn all(width: usize, height: usize) -> [Self; 5] {
+        [
+            Self::Infinite,
+            Self::Torus { width, height },
+            Self::Bounded { width, height },
+            Self::KleinBottle { width, height },
+            Self::CrossSurface { width, height },
+        ]
+    }
+
+    /// Same kind of topology with another size. No effect on the infinite plane.
+    pub fn resized(&self, width: usize, height: usize) -> Self {
+        match self {
+            Self::Infinite => Self::Infinite,
+            Self::Torus { .. } => Self::Torus { width, height },
+            Self::Bounded { .. } => Self::Bounded { width, height },
+            Self::KleinBottle { .. } => Self::KleinBottle { width, height },
+            Self::CrossSurface { .. } => Self::CrossSurface { width, height },
+        }
+    }
+
+    pub fn size(&self) -> Option<(usize, usize)> {
+        match *self {
+            Self::Infinite => None,
+            Self::Torus { width, height }
+            | Self::Bounded { width, height }
+            | Self::KleinBottle { width, height }
+            | Self::CrossSurface { width, height } => Some((width, height)),
+        }
+    }
+
+    /// Bottom left and top right cells of a finite board.
+    pub fn bounds(&self) -> Option<(CellPosition, CellPosition)> {
+        let (width, height) = self.size()?;
+        let min = CellPosition {
+            x: -(width as isize / 2),
+            y: -(height as isize / 2),
+        };
+        let max = CellPosition {
+            x: min.x + width as isize - 1,
+            y: min.y + height as isize - 1,
+        };
+        Some((min, max))
+    }
+
+    /// Whether the edges of the board can be joined without breaking the
+    /// neighbourhoods of the cells of the tiling.
+    ///
+    /// Flipped edges mirror the axial coordinates of hexagons, which does not
+    /// keep them adjacent. Triangles point up or down according to the parity
+    /// of their coordinates, which joined edges must keep: a horizontal flip
+    /// keeps the direction of a triangle and a vertical one reverses it.
+    pub fn supports(&self, tiling: Tiling) -> bool {
+        let is_even = |length: usize| length.is_multiple_of(2);
+        match (self, tiling) {
+            (Self::Infinite | Self::Bounded { .. }, _) | (_, Tiling::Square) => true,
+            (Self::Torus { .. }, Tiling::Hexagonal) => true,
+            (Self::KleinBottle { .. } | Self::CrossSurface { .. }, Tiling::Hexagonal) => false,
+            (Self::Torus { width, height }, Tiling::Triangular) => {
+                is_even(*width) && is_even(*height)
+            }
+            (Self::KleinBottle { width, height }, Tiling::Triangular) => {
+                is_even(*width) && !is_even(*height)
+            }
+            (Self::CrossSurface { .. }, Tiling::Triangular) => false,
+        }
+    }
+
+    /// Maps a position of the plane to the cell it designates on the board.
+    ///
+    /// Returns `None` if the position is outside of a bounded board, or if
+    /// the board is empty. Neighbourhoods are only kept across the edges for
+    /// the tilings the topology [supports](Self::supports).
+    pub fn wrap(&self, pos: &CellPosition) -> Option<CellPosition> {
+        let Some((min, _)) = self.bounds() else {
+            return Some(pos.clone());
+        };
+        let (width, height) = self.size()?;
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let (width, height) = (width as isize, height as isize);
+        let (rel_x, rel_y) = (pos.x - min.x, pos.y - min.y);
+        let (turns_x, mut local_x) = (rel_x.div_euclid(width), rel_x.rem_euclid(width));
+        let (turns_y, mut local_y) = (rel_y.div_euclid(height), rel_y.rem_euclid(height));
+        match self {
+            Self::Infinite => unreachable!(),
+            Self::Torus { .. } => (),
+            Self::Bounded { .. } => {
+                if turns_x != 0 || turns_y != 0 {
+                    return None;
+                }
+            }
+            Self::KleinBottle { .. } => {
+                if turns_y % 2 != 0 {
+                    local_x = width - 1 - local_x;
+                }
+            }
+            Self::CrossSurface { .. } => {
+                if turns_x % 2 != 0 {
+                    local_y = height - 1 - local_y;
+                }
+                if turns_y % 2 != 0 {
+                    local_x = width - 1 - local_x;
+                }
+            }
+        }
+        Some(CellPosition {
+            x: min.x + local_x,
+            y: min.y + local_y,
+        })
+    }
+
+    /// Whether the position is a cell of the board, without any wrapping.
+    pub fn contains(&self, pos: &CellPosition) -> bool {
+        match self.bounds() {
+            None => true,
+            Some((min, max)) => {
+                (min.x..=max.x).contains(&pos.x) && (min.y..=max.y).contains(&pos.y)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Number of times each cell of the board is a neighbour of another one,
+    /// once wrapped.
+    fn neighbourhoods(
+        topology: Topology,
+        tiling: Tiling,
+    ) -> HashMap<(CellPosition, CellPosition), usize> {
+        let mut counts = HashMap::new();
+        let (min, max) = topology.bounds().unwrap();
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let cell = CellPosition { x, y };
+                for (delta_x, delta_y) in tiling.neighbours(&cell) {
+                    let neighbour = CellPosition {
+                        x: x + delta_x,
+                        y: y + delta_y,
+                    };
+                    if let Some(neighbour) = topology.wrap(&neighbour) {
+                        *counts.entry((cell.clone(), neighbour)).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+        counts
+    }
+
+    fn is_symmetric(topology: Topology, tiling: Tiling) -> bool {
+        let counts = neighbourhoods(topology, tiling);
+        counts.iter().all(|((cell, neighbour), count)| {
+            counts.get(&(neighbour.clone(), cell.clone())) == Some(count)
+        })
+    }
+
+    #[test]
+    fn supported_tilings_keep_neighbourhoods_symmetric() {
+        for tiling in Tiling::ALL {
+            for width in 4..=9 {
+                for height in 4..=9 {
+                    for topology in Topology::all(width, height).into_iter().skip(1) {
+                        assert_eq!(
+                            is_symmetric(topology, tiling),
+                            topology.supports(tiling),
+                            "{} {} {width}x{height}",
+                            tiling.name(),
+                            topology.name(),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn cell(x: isize, y: isize) -> CellPosition {
+        CellPosition { x, y }
+    }
+
+    /// Board of 4x3 cells, from (-2, -1) to (1, 1).
+    const WIDTH: usize = 4;
+    const HEIGHT: usize = 3;
+
+    #[test]
+    fn wraps_torus() {
+        let torus = Topology::Torus {
+            width: WIDTH,
+            height: HEIGHT,
+        };
+        assert_eq!(torus.bounds(), Some((cell(-2, -1), cell(1, 1))));
+        assert_eq!(torus.wrap(&cell(0, 0)), Some(cell(0, 0)));
+        assert_eq!(torus.wrap(&cell(2, 0)), Some(cell(-2, 0)));
+        assert_eq!(torus.wrap(&cell(0, 2)), Some(cell(0, -1)));
+        assert_eq!(torus.wrap(&cell(5, 4)), Some(cell(1, 1)));
+        assert!(torus.contains(&cell(1, 1)));
+        assert!(!torus.contains(&cell(2, 0)));
+    }
+
+    #[test]
+    fn rejects_cells_out_of_bounded_boards() {
+        let bounded = Topology::Bounded {
+            width: WIDTH,
+            height: HEIGHT,
+        };
+        assert_eq!(bounded.wrap(&cell(1, 1)), Some(cell(1, 1)));
+        assert_eq!(bounded.wrap(&cell(2, 0)), None);
+        assert_eq!(bounded.wrap(&cell(0, -2)), None);
+        assert!(bounded.contains(&cell(-2, -1)));
+        assert!(!bounded.contains(&cell(-3, 0)));
+        let empty = Topology::Bounded {
+            width: 0,
+            height: HEIGHT,
+        };
+        assert_eq!(empty.wrap(&cell(0, 0)), None);
+    }
+
+    #[test]
+    fn flips_klein_bottle() {
+        let klein = Topology::KleinBottle {
+            width: WIDTH,
+            height: HEIGHT,
+        };
+        // Left and right edges are joined without a flip
+        assert_eq!(klein.wrap(&cell(2, 1)), Some(cell(-2, 1)));
+        // Top and bottom edges flip horizontally
+        assert_eq!(klein.wrap(&cell(0, 2)), Some(cell(-1, -1)));
+        assert_eq!(klein.wrap(&cell(-2, 2)), Some(cell(1, -1)));
+        // Crossing twice flips back
+        assert_eq!(klein.wrap(&cell(0, 5)), Some(cell(0, -1)));
+    }
+
+    #[test]
+    fn flips_cross_surface_twice() {
+        let cross = Topology::CrossSurface {
+            width: WIDTH,
+            height: HEIGHT,
+        };
+        assert_eq!(cross.wrap(&cell(2, 1)), Some(cell(-2, -1)));
+        assert_eq!(cross.wrap(&cell(0, 2)), Some(cell(-1, -1)));
+        // Across a corner, both flips apply
+        assert_eq!(cross.wrap(&cell(2, 2)), Some(cell(1, 1)));
+    }
+
+    #[test]
+    fn wraps_negative_coordinates() {
+        let torus = Topology::Torus {
+            width: WIDTH,
+            height: HEIGHT,
+        };
+        assert_eq!(torus.wrap(&cell(-3, -1)), Some(cell(1, -1)));
+        assert_eq!(torus.wrap(&cell(-7, -4)), Some(cell(1, -1)));
+        let klein = Topology::KleinBottle {
+            width: WIDTH,
+            height: HEIGHT,
+        };
+        assert_eq!(klein.wrap(&cell(0, -2)), Some(cell(-1, 1)));
+        let infinite = Topology::Infinite;
+        assert_eq!(infinite.wrap(&cell(-1000, -7)), Some(cell(-1000, -7)));
+        assert!(infinite.contains(&cell(isize::MIN, isize::MAX)));
+    }
+}
@@ -0,0 +1,358 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Langton's Ant and its generalisations, turmites walking on the cells of a
+//! [`Universe`] and changing their colours.
+//!
+//! The colours are stored as the [states](Universe::state) of the cells, the
+//! universe having to use a rule with enough states, e.g.
+//! [`TurmiteRule::cell_rule`]. The rule of the universe is otherwise ignored
+//! while ants are walking.
+
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::{
+    rule::Rule,
+    universe::{CellPosition, Universe},
+};
+
+/// Direction an ant is facing, north being towards increasing `y`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Heading {
+    #[default]
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Heading {
+    pub const ALL: [Self; 4] = [Self::North, Self::East, Self::South, Self::West];
+
+    /// Offset of the cell in front of an ant.
+    pub fn offset(&self) -> (isize, isize) {
+        match self {
+            Self::North => (0, 1),
+            Self::East => (1, 0),
+            Self::South => (0, -1),
+            Self::West => (-1, 0),
+        }
+    }
+
+    /// Angle from the east, counterclockwise, in degrees.
+    pub fn angle(&self) -> f32 {
+        match self {
+            Self::North => 90.0,
+            Self::East => 0.0,
+            Self::South => 270.0,
+            Self::West => 180.0,
+        }
+    }
+
+    fn turned(&self, turn: Turn) -> Self {
+        let quarters = match turn {
+            Turn::None => 0,
+            Turn::Right => 1,
+            Turn::UTurn => 2,
+            Turn::Left => 3,
+        };
+        Self::ALL[(*self as usize + quarters) % Self::ALL.len()]
+    }
+}
+
+/// Turn of an ant leaving a cell of some colour.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Turn {
+    Left,
+    Right,
+    /// Goes straight on.
+    None,
+    /// Goes back.
+    UTurn,
+}
+
+impl Turn {
+    fn letter(&self) -> char {
+        match self {
+            Self::Left => 'L',
+            Self::Right => 'R',
+            Self::None => 'N',
+            Self::UTurn => 'U',
+        }
+    }
+}
+
+/// Turns of the ants for each colour, e.g. `RL` for Langton's Ant.
+///
+/// An ant on a cell of colour `c` takes the `c`-th turn, changes the colour
+/// of the cell to `c + 1`, the last colour going back to 0, then moves
+/// forward. Each letter is one of `L`, `R`, `N` for no turn and `U` for a
+/// U-turn.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TurmiteRule {
+    turns: Vec<Turn>,
+}
+
+impl TurmiteRule {
+    /// Langton's Ant.
+    pub fn langton() -> Self {
+        Self {
+            turns: vec![Turn::Right, Turn::Left],
+        }
+    }
+
+    pub fn colours(&self) -> u8 {
+        self.turns.len() as u8
+    }
+
+    /// Rule of a universe able to hold the colours, under which no cell is
+    /// ever born.
+    pub fn cell_rule(&self) -> Rule {
+        format!("B/S/C{}", self.colours())
+            .parse()
+            .expect("valid Generations rule")
+    }
+}
+
+impl Default for TurmiteRule {
+    fn default() -> Self {
+        Self::langton()
+    }
+}
+
+impl fmt::Display for TurmiteRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.turns
+            .iter()
+            .try_for_each(|turn| write!(f, "{}", turn.letter()))
+    }
+}
+
+impl FromStr for TurmiteRule {
+    type Err = TurmiteRuleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let turns = s
+            .trim()
+            .chars()
+            .map(|letter| match letter.to_ascii_uppercase() {
+                'L' => Ok(Turn::Left),
+                'R' => Ok(Turn::Right),
+                'N' => Ok(Turn::None),
+                'U' => Ok(Turn::UTurn),
+                _ => Err(TurmiteRuleParseError::InvalidTurn(letter)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if !(2..=u8::MAX as usize).contains(&turns.len()) {
+            return Err(TurmiteRuleParseError::InvalidColours(turns.len()));
+        }
+        Ok(Self { turns })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TurmiteRuleParseError {
+    InvalidTurn(char),
+    InvalidColours(usize),
+}
+
+impl fmt::Display for TurmiteRuleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTurn(letter) => {
+                write!(f, "invalid turn '{letter}', expected L, R, N or U")
+            }
+            Self::InvalidColours(colours) => {
+                write!(f, "invalid number of colours {colours}, expected 2 to 255")
+            }
+        }
+    }
+}
+
+impl Error for TurmiteRuleParseError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ant {
+    pub position: CellPosition,
+    pub heading: Heading,
+}
+
+/// Ants sharing a rule and walking on the same universe.
+#[derive(Clone, Debug, Default)]
+pub struct Turmites {
+    rule: TurmiteRule,
+    ants: Vec<Ant>,
+}
+
+impl Turmites {
+    pub fn new(rule: TurmiteRule) -> Self {
+        Self {
+            rule,
+            ants: Vec::new(),
+        }
+    }
+
+    pub fn rule(&self) -> &TurmiteRule {
+        &self.rule
+    }
+
+    /// Changes the rule, the ants keeping walking from where they are.
+    pub fn set_rule(&mut self, rule: TurmiteRule) {
+        self.rule = rule;
+    }
+
+    pub fn ants(&self) -> &[Ant] {
+        &self.ants
+    }
+
+    pub fn add(&mut self, ant: Ant) {
+        self.ants.push(ant);
+    }
+
+    pub fn clear(&mut self) {
+        self.ants.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ants.is_empty()
+    }
+
+    /// Moves every ant once, in the order they were added, and counts one
+    /// generation.
+    ///
+    /// The ants follow the topology of the universe, turning back at the
+    /// edges of bounded boards. Crossing a mirrored edge of a Klein bottle or
+    /// a cross surface does not mirror their turns.
+    pub fn step(&mut self, universe: &mut Universe) {
+        let topology = universe.topology();
+        let colours = self.rule.colours();
+        for ant in &mut self.ants {
+            let colour = universe.state(&ant.position) % colours;
+            ant.heading = ant.heading.turned(self.rule.turns[colour as usize]);
+            universe.set_state(ant.position.clone(), (colour + 1) % colours);
+            let (dx, dy) = ant.heading.offset();
+            let ahead = CellPosition {
+                x: ant.position.x + dx,
+                y: ant.position.y + dy,
+            };
+            match topology.wrap(&ahead) {
+                Some(ahead) => ant.position = ahead,
+                None => ant.heading = ant.heading.turned(Turn::UTurn),
+            }
+        }
+        universe.set_generation(universe.generation() + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::topology::Topology;
+
+    fn cell(x: isize, y: isize) -> CellPosition {
+        CellPosition { x, y }
+    }
+
+    #[test]
+    fn turns_headings() {
+        assert_eq!(Heading::North.turned(Turn::Right), Heading::East);
+        assert_eq!(Heading::North.turned(Turn::Left), Heading::West);
+        assert_eq!(Heading::North.turned(Turn::UTurn), Heading::South);
+        assert_eq!(Heading::North.turned(Turn::None), Heading::North);
+        assert_eq!(Heading::West.turned(Turn::Right), Heading::North);
+        assert_eq!(Heading::South.turned(Turn::Left), Heading::East);
+    }
+
+    #[test]
+    fn langton_ant_walks() {
+        let rule = TurmiteRule::langton();
+        let mut universe = Universe::new(rule.cell_rule(), Topology::Infinite);
+        let mut turmites = Turmites::new(rule);
+        turmites.add(Ant {
+            position: cell(0, 0),
+            heading: Heading::North,
+        });
+        // Turning right on each new cell of a square, then left on the first one
+        let expected = [
+            (cell(1, 0), Heading::East),
+            (cell(1, -1), Heading::South),
+            (cell(0, -1), Heading::West),
+            (cell(0, 0), Heading::North),
+            (cell(-1, 0), Heading::West),
+        ];
+        for (position, heading) in expected {
+            turmites.step(&mut universe);
+            assert_eq!(turmites.ants()[0], Ant { position, heading });
+        }
+        assert_eq!(universe.generation(), 5);
+        assert_eq!(universe.state(&cell(0, 0)), 0);
+        for colored in [cell(1, 0), cell(1, -1), cell(0, -1)] {
+            assert_eq!(universe.state(&colored), 1);
+        }
+        assert_eq!(universe.state(&cell(-1, 0)), 0);
+    }
+
+    #[test]
+    fn turns_back_at_bounded_edges() {
+        let rule: TurmiteRule = "NN".parse().unwrap();
+        let topology = Topology::Bounded {
+            width: 3,
+            height: 3,
+        };
+        let mut universe = Universe::new(rule.cell_rule(), topology);
+        let mut turmites = Turmites::new(rule);
+        turmites.add(Ant {
+            position: cell(1, 0),
+            heading: Heading::East,
+        });
+        turmites.step(&mut universe);
+        let turned = Ant {
+            position: cell(1, 0),
+            heading: Heading::West,
+        };
+        assert_eq!(turmites.ants()[0], turned);
+        assert_eq!(universe.state(&cell(1, 0)), 1);
+        turmites.step(&mut universe);
+        assert_eq!(turmites.ants()[0].position, cell(0, 0));
+        assert_eq!(universe.state(&cell(1, 0)), 0);
+    }
+
+    #[test]
+    fn parses_rules() {
+        assert_eq!("rl".parse(), Ok(TurmiteRule::langton()));
+        let rule: TurmiteRule = " LRUN ".parse().unwrap();
+        assert_eq!(rule.to_string(), "LRUN");
+        assert_eq!(rule.colours(), 4);
+        assert_eq!(rule.cell_rule().states(), 4);
+        assert!("L".repeat(255).parse::<TurmiteRule>().is_ok());
+        for (rule, error) in [
+            ("R", TurmiteRuleParseError::InvalidColours(1)),
+            ("", TurmiteRuleParseError::InvalidColours(0)),
+            ("RX", TurmiteRuleParseError::InvalidTurn('X')),
+            (&"L".repeat(256), TurmiteRuleParseError::InvalidColours(256)),
+        ] {
+            assert_eq!(rule.parse::<TurmiteRule>(), Err(error), "{rule}");
+        }
+    }
+}
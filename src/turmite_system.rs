@@ -0,0 +1,66 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use crate::cell_system::{
+    CellHistory, CellParams, CellSet, CellUniverse, NextGenTimer, SimulationStats,
+};
+use bevy::prelude::*;
+use game_of_life::{history::Change, turmite::Turmites};
+
+/// Ants walking on the [`CellUniverse`], stepped instead of its rule while
+/// there are any.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct CellTurmites(pub Turmites);
+
+pub struct TurmiteSystem;
+
+impl Plugin for TurmiteSystem {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CellTurmites>()
+            .add_systems(Update, system_turmites.in_set(CellSet).run_if(ants_walking));
+    }
+}
+
+pub fn ants_walking(turmites: Res<CellTurmites>) -> bool {
+    !turmites.is_empty()
+}
+
+fn system_turmites(
+    mut timer: ResMut<NextGenTimer>,
+    mut cell_params: ResMut<CellParams>,
+    mut turmites: ResMut<CellTurmites>,
+    mut universe: ResMut<CellUniverse>,
+    mut history: ResMut<CellHistory>,
+    mut stats: ResMut<SimulationStats>,
+    time: Res<Time>,
+) {
+    if !timer.is_step_due(&mut cell_params, &time) {
+        return;
+    }
+    let before = universe.snapshot();
+    turmites.step(&mut universe);
+    let change = Change::between(&before, &universe);
+    stats.record_step(&universe, &change);
+    history.push(change);
+}
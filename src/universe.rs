@@ -0,0 +1,875 @@
+/*
+MIT License
+
+Copyright (c) 2023 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::OnceLock,
+    thread,
+};
+
+use crate::{
+    dense::DenseGrid,
+    hashlife::{self, HashLife},
+    larger_than_life, parallel,
+    pattern::Pattern,
+    rule::Rule,
+    rule_table,
+    topology::Topology,
+};
+
+/// Rows of cells handled by the same thread when computing in parallel.
+const SHARD_HEIGHT: isize = 16;
+
+/// Cells dispatched by a thread, indexed by shard.
+type Shards<T> = Vec<Vec<T>>;
+
+/// Neighbour of an alive cell, with the weight of the alive cell in its neighbourhood.
+type WeightedNeighbour = (CellPosition, usize);
+
+#[derive(Clone, PartialEq, Eq, Debug, Hash, PartialOrd, Ord)]
+pub struct CellPosition {
+    pub x: isize,
+    pub y: isize,
+}
+
+/// Algorithm used to compute the next generations.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Engine {
+    /// Counts the neighbours of every alive cell, one generation at a time.
+    #[default]
+    Sparse,
+    /// Jumps `2^n` generations at once, on the infinite plane only.
+    HashLife,
+    /// Packs the cells of a finite board in bits, for dense soups.
+    Dense,
+}
+
+impl Engine {
+    pub const ALL: [Self; 3] = [Self::Sparse, Self::HashLife, Self::Dense];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sparse => "Sparse",
+            Self::HashLife => "HashLife",
+            Self::Dense => "Dense",
+        }
+    }
+
+    pub fn supports(&self, rule: &Rule, topology: &Topology) -> bool {
+        match self {
+            Self::Sparse => true,
+            Self::HashLife => {
+                rule.states() == 2
+                    && rule.has_life_neighbourhood()
+                    && topology == &Topology::Infinite
+            }
+            Self::Dense => {
+                rule.states() == 2
+                    && rule.has_life_neighbourhood()
+                    && !rule.is_non_totalistic()
+                    && topology.size().is_some()
+            }
+        }
+    }
+}
+
+/// Saved state of the cells of a [`Universe`].
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+    cells: HashSet<CellPosition>,
+    dying: HashMap<CellPosition, u8>,
+    generation: u64,
+}
+
+impl Snapshot {
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Number of stored cells, alive or dying.
+    pub fn cell_count(&self) -> usize {
+        self.cells.len() + self.dying.len()
+    }
+
+    pub fn get(&self, pos: &CellPosition) -> bool {
+        self.cells.contains(pos)
+    }
+
+    pub fn iter_alive(&self) -> impl Iterator<Item = &CellPosition> {
+        self.cells.iter()
+    }
+
+    /// See [`Universe::state`].
+    pub fn state(&self, pos: &CellPosition) -> u8 {
+        state(&self.cells, &self.dying, pos)
+    }
+
+    pub fn iter_dying(&self) -> impl Iterator<Item = (&CellPosition, u8)> {
+        self.dying.iter().map(|(cell, state)| (cell, *state))
+    }
+}
+
+/// Set of alive cells evolving according to a rule, without any rendering.
+pub struct Universe {
+    rule: Rule,
+    topology: Topology,
+    engine: Engine,
+    /// Alive cells, built from the representation of [`Self::store`] when needed.
+    cells: OnceLock<HashSet<CellPosition>>,
+    /// Engine whose own representation of the alive cells is up to date, so
+    /// that following generations do not need to load them again.
+    store: Engine,
+    /// Cells which are neither alive nor dead, with their state: the dying
+    /// cells of Generations rules, or the cells in any other state of a rule table.
+    dying: HashMap<CellPosition, u8>,
+    generation: u64,
+    hashlife: HashLife,
+    dense: DenseGrid,
+}
+
+impl Default for Universe {
+    fn default() -> Self {
+        Self::new(Rule::default(), Topology::default())
+    }
+}
+
+impl Universe {
+    pub fn new(rule: Rule, topology: Topology) -> Self {
+        Self {
+            hashlife: HashLife::new(rule.clone()),
+            dense: DenseGrid::new(),
+            rule,
+            topology,
+            engine: Engine::default(),
+            cells: OnceLock::from(HashSet::new()),
+            store: Engine::Sparse,
+            dying: HashMap::new(),
+            generation: 0,
+        }
+    }
+
+    pub fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    /// Changes the rule. Dying cells past the number of states of the new rule die.
+    pub fn set_rule(&mut self, rule: Rule) {
+        let states = rule.states();
+        self.dying.retain(|_, state| *state < states);
+        self.rule = rule;
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Changes the topology. Cells out of a finite board die on the next generation.
+    pub fn set_topology(&mut self, topology: Topology) {
+        if topology != self.topology {
+            // The dense grid only holds the cells of the previous board
+            self.alive_mut();
+        }
+        self.topology = topology;
+    }
+
+    pub fn engine(&self) -> Engine {
+        self.engine
+    }
+
+    /// Selects the engine, which falls back to [`Engine::Sparse`] if it does
+    /// not support the current topology.
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+    }
+
+    /// Number of generations computed since the universe was created or cleared.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn population(&self) -> usize {
+        match (self.cells.get(), self.store) {
+            (None, Engine::HashLife) => self.hashlife.population() as usize,
+            (None, Engine::Dense) => self.dense.population(),
+            _ => self.alive().len(),
+        }
+    }
+
+    pub fn get(&self, pos: &CellPosition) -> bool {
+        self.alive().contains(pos)
+    }
+
+    /// State of a cell: 0 if dead, 1 if alive, and from 2 to `states - 1`
+    /// while dying with Generations rules or for the other states of rule tables.
+    pub fn state(&self, pos: &CellPosition) -> u8 {
+        state(self.alive(), &self.dying, pos)
+    }
+
+    /// Makes a cell alive or dead, including a dying one.
+    pub fn set(&mut self, pos: CellPosition, alive: bool) {
+        self.dying.remove(&pos);
+        if alive {
+            self.alive_mut().insert(pos);
+        } else {
+            self.alive_mut().remove(&pos);
+        }
+    }
+
+    /// Makes an alive cell dead, or any other cell alive.
+    pub fn toggle(&mut self, pos: CellPosition) {
+        if !self.alive_mut().remove(&pos) {
+            self.dying.remove(&pos);
+            self.alive_mut().insert(pos);
+        }
+    }
+
+    /// Sets the state of a cell, see [`Self::state`]. States which do not
+    /// exist in the rule are ignored.
+    pub fn set_state(&mut self, pos: CellPosition, state: u8) {
+        match state {
+            0 | 1 => self.set(pos, state == 1),
+            _ if state < self.rule.states() => {
+                self.alive_mut().remove(&pos);
+                self.dying.insert(pos, state);
+            }
+            _ => (),
+        }
+    }
+
+    /// Sets the state of a cell which is not alive, 0 meaning dead.
+    pub(crate) fn set_dying(&mut self, pos: CellPosition, state: u8) {
+        if state == 0 {
+            self.dying.remove(&pos);
+        } else {
+            self.dying.insert(pos, state);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.replace_alive(HashSet::new());
+        self.dying.clear();
+        self.generation = 0;
+    }
+
+    /// Cells which are not dead within a box given by its bottom left and top
+    /// right corners, as a pattern whose bottom left corner is the origin.
+    pub fn region(&self, min: &CellPosition, max: &CellPosition) -> Pattern {
+        let relative = |cell: &CellPosition| CellPosition {
+            x: cell.x - min.x,
+            y: cell.y - min.y,
+        };
+        Pattern {
+            cells: self
+                .alive()
+                .iter()
+                .filter(|cell| is_within(cell, min, max))
+                .map(relative)
+                .collect(),
+            states: self
+                .dying
+                .iter()
+                .filter(|(cell, _)| is_within(cell, min, max))
+                .map(|(cell, state)| (relative(cell), *state))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Kills the cells within a box, see [`Self::region`].
+    pub fn clear_region(&mut self, min: &CellPosition, max: &CellPosition) {
+        self.alive_mut().retain(|cell| !is_within(cell, min, max));
+        self.dying.retain(|cell, _| !is_within(cell, min, max));
+    }
+
+    /// Sets the state of all the cells of the board within a box, see
+    /// [`Self::region`] and [`Self::set_state`].
+    pub fn fill_region(&mut self, min: &CellPosition, max: &CellPosition, state: u8) {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let cell = CellPosition { x, y };
+                if self.topology.contains(&cell) {
+                    self.set_state(cell, state);
+                }
+            }
+        }
+    }
+
+    /// Sets the state of the cells connected to `start` through edges and in
+    /// the same state as it, without going out of a box, see [`Self::region`].
+    pub fn flood_fill(
+        &mut self,
+        start: CellPosition,
+        state: u8,
+        min: &CellPosition,
+        max: &CellPosition,
+    ) {
+        let replaced = self.state(&start);
+        if replaced == state || !is_within(&start, min, max) || !self.topology.contains(&start) {
+            return;
+        }
+        let tiling = self.rule.tiling();
+        let mut pending = vec![start];
+        while let Some(cell) = pending.pop() {
+            if self.state(&cell) != replaced {
+                continue;
+            }
+            for (dx, dy) in tiling.adjacent(&cell) {
+                let next = CellPosition {
+                    x: cell.x + dx,
+                    y: cell.y + dy,
+                };
+                if is_within(&next, min, max)
+                    && self.topology.contains(&next)
+                    && self.state(&next) == replaced
+                {
+                    pending.push(next);
+                }
+            }
+            self.set_state(cell, state);
+        }
+    }
+
+    /// Adds the cells of a pattern moved by an offset, the cells out of the
+    /// board being wrapped around it or ignored according to the topology.
+    /// The cells around the pattern are kept.
+    pub fn paste(&mut self, pattern: &Pattern, offset: &CellPosition) {
+        let alive = pattern.cells.iter().map(|cell| (cell, 1));
+        let states = pattern.states.iter().map(|(cell, state)| (cell, *state));
+        for (cell, state) in alive.chain(states) {
+            let moved = CellPosition {
+                x: cell.x + offset.x,
+                y: cell.y + offset.y,
+            };
+            if let Some(cell) = self.topology.wrap(&moved) {
+                self.set_state(cell, state);
+            }
+        }
+    }
+
+    pub fn iter_alive(&self) -> impl Iterator<Item = &CellPosition> {
+        self.alive().iter()
+    }
+
+    pub fn iter_dying(&self) -> impl Iterator<Item = (&CellPosition, u8)> {
+        self.dying.iter().map(|(cell, state)| (cell, *state))
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            cells: self.alive().clone(),
+            dying: self.dying.clone(),
+            generation: self.generation,
+        }
+    }
+
+    /// Replaces the cells and the generation counter with saved ones.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.replace_alive(snapshot.cells.clone());
+        self.dying.clone_from(&snapshot.dying);
+        self.generation = snapshot.generation;
+    }
+
+    /// Whether the cells and generation are the same as the saved ones.
+    pub fn matches(&self, snapshot: &Snapshot) -> bool {
+        self.generation == snapshot.generation
+            && *self.alive() == snapshot.cells
+            && self.dying == snapshot.dying
+    }
+
+    pub(crate) fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    /// Bottom left and top right corners of the smallest box containing all
+    /// the cells which are not dead.
+    pub fn bounding_box(&self) -> Option<(CellPosition, CellPosition)> {
+        let mut cells = self.alive().iter().chain(self.dying.keys());
+        let first = cells.next()?;
+        let (mut min, mut max) = (first.clone(), first.clone());
+        for cell in cells {
+            min.x = min.x.min(cell.x);
+            min.y = min.y.min(cell.y);
+            max.x = max.x.max(cell.x);
+            max.y = max.y.max(cell.y);
+        }
+        Some((min, max))
+    }
+
+    /// Computes the next generation.
+    pub fn step(&mut self) {
+        self.step_pow2(0);
+    }
+
+    /// Computes `2^step_log2` generations, in one jump when using [`Engine::HashLife`]
+    /// and `step_log2` is at most [`hashlife::MAX_STEP_LOG2`].
+    ///
+    /// Generations being counted on 64 bits, `step_log2` is at most 63.
+    pub fn step_pow2(&mut self, step_log2: u8) {
+        self.advance(1_u64 << step_log2.min(u64::BITS as u8 - 1));
+    }
+
+    /// Computes the given number of generations, without going past the
+    /// largest generation number.
+    ///
+    /// [`Engine::HashLife`] stops early if the pattern could grow out of the
+    /// coordinates range or past [`hashlife::MAX_POPULATION`] cells, the
+    /// generation number only counting the computed generations.
+    pub fn advance(&mut self, generations: u64) {
+        let mut generations = generations.min(u64::MAX - self.generation);
+        if generations == 0 {
+            return;
+        }
+        match self.active_engine() {
+            Engine::HashLife => {
+                self.hashlife.set_rule(&self.rule);
+                if self.store != Engine::HashLife {
+                    self.alive();
+                    self.hashlife.load(self.cells.get().into_iter().flatten());
+                    self.store = Engine::HashLife;
+                }
+                let mut computed = 0;
+                'steps: for step_log2 in (0..u64::BITS as u8).rev() {
+                    if generations & (1 << step_log2) == 0 {
+                        continue;
+                    }
+                    // Larger jumps are split into the largest supported ones.
+                    let jump_log2 = step_log2.min(hashlife::MAX_STEP_LOG2);
+                    for _ in 0..1_u64 << (step_log2 - jump_log2) {
+                        if !self.hashlife.step(jump_log2) {
+                            break 'steps;
+                        }
+                        computed += 1 << jump_log2;
+                    }
+                }
+                generations = computed;
+                if computed > 0 {
+                    self.cells = OnceLock::new();
+                }
+            }
+            Engine::Dense => {
+                self.load_dense();
+                for _ in 0..generations {
+                    self.dense.step(&self.rule);
+                }
+                self.cells = OnceLock::new();
+            }
+            Engine::Sparse => {
+                self.alive_mut();
+                for _ in 0..generations {
+                    self.next_generation_sparse();
+                }
+            }
+        }
+        self.generation += generations;
+    }
+
+    /// Computes the next generation with [`Engine::Dense`], returning the
+    /// cells which died and the ones which were born. Nothing is computed if
+    /// another engine is in use.
+    pub(crate) fn step_dense(&mut self) -> Option<(Vec<CellPosition>, Vec<CellPosition>)> {
+        if self.active_engine() != Engine::Dense || self.generation == u64::MAX {
+            return None;
+        }
+        self.load_dense();
+        self.dense.step(&self.rule);
+        let (deaths, births) = self.dense.last_changes();
+        // Updating the alive cells costs less than building them again
+        if let Some(cells) = self.cells.get_mut() {
+            for cell in &deaths {
+                cells.remove(cell);
+            }
+            cells.extend(births.iter().cloned());
+        }
+        self.generation += 1;
+        Some((deaths, births))
+    }
+
+    /// Selected engine, or [`Engine::Sparse`] if it does not support the rule
+    /// or the topology.
+    fn active_engine(&self) -> Engine {
+        if self.engine.supports(&self.rule, &self.topology) {
+            self.engine
+        } else {
+            Engine::Sparse
+        }
+    }
+
+    /// Alive cells, built from the engine storing them if needed.
+    fn alive(&self) -> &HashSet<CellPosition> {
+        self.cells.get_or_init(|| match self.store {
+            Engine::Sparse => HashSet::new(),
+            Engine::HashLife => self.hashlife.cells().into_iter().collect(),
+            Engine::Dense => self.dense.cells().into_iter().collect(),
+        })
+    }
+
+    /// Alive cells to be modified, the engine storing them being out of date
+    /// afterwards.
+    fn alive_mut(&mut self) -> &mut HashSet<CellPosition> {
+        self.alive();
+        self.store = Engine::Sparse;
+        self.cells.get_mut().expect("alive cells were just built")
+    }
+
+    fn replace_alive(&mut self, cells: HashSet<CellPosition>) {
+        self.cells = OnceLock::from(cells);
+        self.store = Engine::Sparse;
+    }
+
+    /// Loads the alive cells into the dense grid, unless it already holds them.
+    fn load_dense(&mut self) {
+        if self.store != Engine::Dense {
+            self.alive();
+            self.dense
+                .load(self.topology, self.cells.get().into_iter().flatten());
+            self.store = Engine::Dense;
+        }
+    }
+
+    fn next_generation_sparse(&mut self) {
+        if let Some(table) = self.rule.table() {
+            let (cells, dying) =
+                rule_table::next_generation(self.alive(), &self.dying, table, self.topology);
+            self.replace_alive(cells);
+            self.dying = dying;
+            return;
+        }
+        if self.rule.is_larger_than_life() {
+            let (cells, deaths) = larger_than_life::next_generation(
+                self.alive(),
+                &self.dying,
+                &self.rule,
+                self.topology,
+            );
+            self.replace_alive(cells);
+            self.decay(deaths);
+            return;
+        }
+        let threads = parallel::thread_count(self.alive().len());
+        if threads > 1 {
+            self.next_generation_sparse_parallel(threads);
+            return;
+        }
+        let topology = self.topology;
+        let mut neighbours = HashMap::new();
+        // Compute number of alive neighbour cells
+        for cell in self.alive().iter().filter(|cell| topology.contains(cell)) {
+            for (index, pos_delta) in self.rule.tiling().neighbours(cell).iter().enumerate() {
+                let Some(scan_pos) = topology.wrap(&CellPosition {
+                    x: cell.x + pos_delta.0,
+                    y: cell.y + pos_delta.1,
+                }) else {
+                    continue;
+                };
+                *neighbours.entry(scan_pos).or_insert(0) += self.rule.neighbour_weight(index);
+            }
+        }
+        let births: Vec<CellPosition> = neighbours
+            .iter()
+            .filter(|(cell, neighbours_count)| {
+                self.rule.is_birth(**neighbours_count)
+                    && !self.alive().contains(cell)
+                    && !self.dying.contains_key(cell)
+            })
+            .map(|(cell, _)| cell.clone())
+            .collect();
+        // Killing cells which do not match the survival conditions, or are out of the board
+        let mut deaths = Vec::new();
+        self.alive_mut();
+        let cells = self.cells.get_mut().expect("sparse cells are built");
+        cells.retain(|cell| {
+            let neighbours_count = *neighbours.get(cell).unwrap_or(&0);
+            let survives = self.rule.is_survival(neighbours_count) && topology.contains(cell);
+            if !survives {
+                deaths.push(cell.clone());
+            }
+            survives
+        });
+        self.decay(deaths);
+        // Spawn new cells
+        self.alive_mut().extend(births);
+    }
+
+    /// Moves dying cells to their next state, cells which just died becoming dying ones.
+    fn decay(&mut self, deaths: Vec<CellPosition>) {
+        let (states, topology) = (self.rule.states(), self.topology);
+        if states == 2 {
+            return;
+        }
+        self.dying.retain(|cell, state| {
+            *state += 1;
+            *state < states && topology.contains(cell)
+        });
+        self.dying.extend(
+            deaths
+                .into_iter()
+                .filter(|cell| topology.contains(cell))
+                .map(|cell| (cell, 2)),
+        );
+    }
+
+    /// Same as [`Self::next_generation_sparse`], the neighbour counts of each
+    /// band of [`SHARD_HEIGHT`] rows being computed by a single thread so that
+    /// no merge of counts is needed.
+    fn next_generation_sparse_parallel(&mut self, threads: usize) {
+        let (topology, rule, cells, dying) = (self.topology, &self.rule, self.alive(), &self.dying);
+        let shard = |cell: &CellPosition| {
+            cell.y.div_euclid(SHARD_HEIGHT).rem_euclid(threads as isize) as usize
+        };
+        let alive: Vec<&CellPosition> = cells
+            .iter()
+            .filter(|cell| topology.contains(cell))
+            .collect();
+        // Alive cells and their neighbours, dispatched by shard
+        let dispatched: Vec<(Shards<&CellPosition>, Shards<WeightedNeighbour>)> =
+            thread::scope(|scope| {
+                let handles: Vec<_> = alive
+                    .chunks(alive.len().div_ceil(threads))
+                    .map(|chunk| {
+                        scope.spawn(move || {
+                            let mut alive_shards = vec![Vec::new(); threads];
+                            let mut neighbour_shards = vec![Vec::new(); threads];
+                            for cell in chunk {
+                                alive_shards[shard(cell)].push(*cell);
+                                for (index, pos_delta) in
+                                    rule.tiling().neighbours(cell).iter().enumerate()
+                                {
+                                    if let Some(scan_pos) = topology.wrap(&CellPosition {
+                                        x: cell.x + pos_delta.0,
+                                        y: cell.y + pos_delta.1,
+                                    }) {
+                                        neighbour_shards[shard(&scan_pos)]
+                                            .push((scan_pos, rule.neighbour_weight(index)));
+                                    }
+                                }
+                            }
+                            (alive_shards, neighbour_shards)
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().unwrap())
+                    .collect()
+            });
+        let next: Vec<(Vec<CellPosition>, Vec<CellPosition>)> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads)
+                .map(|shard_idx| {
+                    let dispatched = &dispatched;
+                    scope.spawn(move || {
+                        let mut neighbours = HashMap::new();
+                        for (_, neighbour_shards) in dispatched {
+                            for (scan_pos, weight) in &neighbour_shards[shard_idx] {
+                                *neighbours.entry(scan_pos).or_insert(0) += weight;
+                            }
+                        }
+                        let mut next: Vec<CellPosition> = neighbours
+                            .iter()
+                            .filter(|(cell, neighbours_count)| {
+                                rule.is_birth(**neighbours_count)
+                                    && !cells.contains(**cell)
+                                    && !dying.contains_key(**cell)
+                            })
+                            .map(|(cell, _)| (*cell).clone())
+                            .collect();
+                        let mut deaths = Vec::new();
+                        for (alive_shards, _) in dispatched {
+                            for cell in &alive_shards[shard_idx] {
+                                if rule.is_survival(*neighbours.get(*cell).unwrap_or(&0)) {
+                                    next.push((*cell).clone());
+                                } else {
+                                    deaths.push((*cell).clone());
+                                }
+                            }
+                        }
+                        (next, deaths)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        let (next, deaths): (Vec<_>, Vec<_>) = next.into_iter().unzip();
+        self.replace_alive(next.into_iter().flatten().collect());
+        self.decay(deaths.into_iter().flatten().collect());
+    }
+}
+
+fn state(
+    cells: &HashSet<CellPosition>,
+    dying: &HashMap<CellPosition, u8>,
+    pos: &CellPosition,
+) -> u8 {
+    if cells.contains(pos) {
+        1
+    } else {
+        dying.get(pos).copied().unwrap_or(0)
+    }
+}
+
+/// Whether a cell is within a box given by its bottom left and top right corners.
+fn is_within(cell: &CellPosition, min: &CellPosition, max: &CellPosition) -> bool {
+    (min.x..=max.x).contains(&cell.x) && (min.y..=max.y).contains(&cell.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    /// Random cells around the origin, half of the square being alive.
+    fn soup(rule: Rule, topology: Topology, size: isize, seed: u64) -> Universe {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut universe = Universe::new(rule, topology);
+        for y in -size / 2..size - size / 2 {
+            for x in -size / 2..size - size / 2 {
+                if rng.gen_bool(0.5) {
+                    universe.set(CellPosition { x, y }, true);
+                }
+            }
+        }
+        universe
+    }
+
+    fn sorted_cells(universe: &Universe) -> Vec<CellPosition> {
+        let mut cells: Vec<CellPosition> = universe.iter_alive().cloned().collect();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn hashlife_matches_sparse() {
+        for rule in [Rule::conway(), "B36/S23".parse().unwrap()] {
+            let mut sparse = soup(rule, Topology::Infinite, 40, 5);
+            let mut hashlife = soup(sparse.rule().clone(), Topology::Infinite, 40, 5);
+            hashlife.set_engine(Engine::HashLife);
+            for generations in [1, 7, 64, 100] {
+                sparse.advance(generations);
+                hashlife.advance(generations);
+                assert_eq!(hashlife.generation(), sparse.generation());
+                assert_eq!(sorted_cells(&hashlife), sorted_cells(&sparse));
+            }
+            sparse.step_pow2(5);
+            hashlife.step_pow2(5);
+            assert_eq!(sorted_cells(&hashlife), sorted_cells(&sparse));
+        }
+    }
+
+    #[test]
+    fn hashlife_jumps_far() {
+        let mut universe = Universe::new(Rule::conway(), Topology::Infinite);
+        // Glider moving by (1, -1) every 4 generations
+        for (x, y) in [(1, 0), (2, -1), (0, -2), (1, -2), (2, -2)] {
+            universe.set(CellPosition { x, y }, true);
+        }
+        let glider = sorted_cells(&universe);
+        universe.set_engine(Engine::HashLife);
+        universe.step_pow2(40);
+        assert_eq!(universe.generation(), 1 << 40);
+        let shift = 1 << 38;
+        let moved: Vec<CellPosition> = glider
+            .iter()
+            .map(|cell| CellPosition {
+                x: cell.x + shift,
+                y: cell.y - shift,
+            })
+            .collect();
+        assert_eq!(sorted_cells(&universe), moved);
+        // Generations never go past the largest generation number
+        universe.step_pow2(u8::MAX);
+        assert!(universe.generation() > 1 << 40);
+        assert_eq!(universe.population(), glider.len());
+    }
+
+    #[test]
+    fn dense_matches_sparse() {
+        // Widths across several words, the last one being partially used
+        for (width, height) in [(70, 33), (129, 20)] {
+            for topology in Topology::all(width, height).into_iter().skip(1) {
+                for rule in [Rule::conway(), "B3678/S34678".parse().unwrap()] {
+                    // Soup larger than the board, cells outside being ignored
+                    let mut sparse = soup(rule, topology, 140, 12);
+                    let mut dense = soup(sparse.rule().clone(), topology, 140, 12);
+                    dense.set_engine(Engine::Dense);
+                    for generations in [1, 10, 40] {
+                        sparse.advance(generations);
+                        dense.advance(generations);
+                        assert_eq!(
+                            sorted_cells(&dense),
+                            sorted_cells(&sparse),
+                            "{} {width}x{height}",
+                            topology.name()
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Larger than Life and rule tables are never computed in parallel.
+    #[test]
+    fn parallel_matches_sequential() {
+        let rules: [Rule; 3] = [
+            Rule::conway(),
+            "B2/S/C3".parse().unwrap(),
+            "B2ci3ai4c8/S02ea3eikjq4iz5ar6i7e".parse().unwrap(),
+        ];
+        for topology in Topology::all(75, 60) {
+            for rule in &rules {
+                let mut sequential = soup(rule.clone(), topology, 80, 13);
+                let mut parallel = soup(rule.clone(), topology, 80, 13);
+                for _ in 0..15 {
+                    sequential.next_generation_sparse();
+                    parallel.next_generation_sparse_parallel(3);
+                    assert_eq!(sorted_cells(&parallel), sorted_cells(&sequential));
+                    assert_eq!(parallel.dying, sequential.dying, "{}", topology.name());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hashlife_stops_before_growing_too_large() {
+        let gun = crate::library::find("Gosper glider gun").unwrap().pattern();
+        let mut universe = Universe::new(Rule::conway(), Topology::Infinite);
+        universe.paste(&gun, &CellPosition { x: 0, y: 0 });
+        universe.set_engine(Engine::HashLife);
+        universe.step_pow2(20);
+        // One glider every 30 generations
+        let population = universe.population();
+        assert!(population > (1 << 20) / 30 * 5);
+        // Millions of gliders after 2^30 generations
+        universe.step_pow2(30);
+        assert_eq!(universe.generation(), 1 << 20);
+        assert_eq!(universe.population(), population);
+        assert_eq!(universe.iter_alive().count(), population);
+    }
+}